@@ -0,0 +1,269 @@
+//! Async mutations, eg. submitting a form, with their pending state and outcome as signals.
+//!
+//! ```rust,ignore
+//! let save = Action::new(|todo: Todo| async move { api::save(&todo).await });
+//! let submit = save.callback_async(move |save, _| {
+//!     save.dispatch_optimistic(todo(), &todos, |todos| todos.lock_mut().push_cloned(todo()))
+//! });
+//! html! {
+//!     <form on:submit=submit>
+//!         <button disabled=save.pending().map(|pending| pending)>"Save"</button>
+//!         <p class="error">{save.error().map(|error| error.unwrap_or_default())}</p>
+//!     </form>
+//! }
+//! ```
+use crate::{callback::Callback, BoxedLocal};
+use futures_signals::{
+    signal::{Mutable, Signal},
+    signal_vec::MutableVec,
+};
+use std::{cell::Cell, future::Future, rc::Rc};
+
+/// State that an optimistic update can be applied to, and rolled back from if the action fails.
+pub trait Optimistic: Clone + 'static {
+    type Snapshot: 'static;
+
+    fn snapshot(&self) -> Self::Snapshot;
+
+    fn restore(&self, snapshot: Self::Snapshot);
+}
+
+impl<T: Clone + 'static> Optimistic for Mutable<T> {
+    type Snapshot = T;
+
+    fn snapshot(&self) -> T {
+        self.get_cloned()
+    }
+
+    fn restore(&self, snapshot: T) {
+        self.set(snapshot);
+    }
+}
+
+impl<T: Clone + 'static> Optimistic for MutableVec<T> {
+    type Snapshot = Vec<T>;
+
+    fn snapshot(&self) -> Vec<T> {
+        self.lock_ref().to_vec()
+    }
+
+    fn restore(&self, snapshot: Vec<T>) {
+        self.lock_mut().replace_cloned(snapshot);
+    }
+}
+
+/// Restores `state` to `snapshot` once dropped, unless the update was [kept](Rollback::keep).
+struct Rollback<S: Optimistic> {
+    state: S,
+    snapshot: Option<S::Snapshot>,
+}
+
+impl<S: Optimistic> Rollback<S> {
+    fn keep(mut self) {
+        self.snapshot = None;
+    }
+}
+
+impl<S: Optimistic> Drop for Rollback<S> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.state.restore(snapshot);
+        }
+    }
+}
+
+/// Counts a dispatch as running until it completes or is dropped.
+struct Running(Mutable<usize>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.replace_with(|running| *running - 1);
+    }
+}
+
+type Handler<I, O, E> = Box<dyn Fn(I) -> BoxedLocal<Result<O, E>>>;
+
+struct Inner<I, O, E> {
+    handler: Handler<I, O, E>,
+    /// The number of dispatches that have not completed yet.
+    running: Mutable<usize>,
+    value: Mutable<Option<O>>,
+    error: Mutable<Option<E>>,
+    /// Bumped on every dispatch, only the latest one sets `value` and `error`.
+    version: Cell<usize>,
+}
+
+/// An async function, eg. a request to an API, that is dispatched with an input of type `I`.
+///
+/// The action is dispatched with [`Action::dispatch`], which returns a future to spawn, eg. from
+/// an async `on:` handler or [`Callback::callback_async`]. The outcome of the latest dispatch is
+/// exposed through [`Action::value`] and [`Action::error`].
+pub struct Action<I, O, E = String> {
+    inner: Rc<Inner<I, O, E>>,
+}
+
+impl<I, O, E> Clone for Action<I, O, E> {
+    fn clone(&self) -> Self {
+        Action {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<I: 'static, O: Clone + 'static, E: Clone + 'static> Action<I, O, E> {
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn(I) -> Fut + 'static,
+        Fut: Future<Output = Result<O, E>> + 'static,
+    {
+        Action {
+            inner: Rc::new(Inner {
+                handler: Box::new(move |input| Box::pin(f(input))),
+                running: Mutable::new(0),
+                value: Mutable::new(None),
+                error: Mutable::new(None),
+                version: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Runs the action with `input`. The returned future has to be spawned or awaited.
+    ///
+    /// The error of the previous dispatch is cleared right away, and the value is kept until this
+    /// one succeeds. When dispatches overlap, only the latest one sets the value and the error.
+    pub fn dispatch(&self, input: I) -> impl Future<Output = ()> {
+        self.run(input, |_| {})
+    }
+
+    /// Like [`Action::dispatch`], applying `update` to `state` right away. If the action fails, or
+    /// the returned future is dropped before it completes, `state` is restored to what it was
+    /// before `update`, discarding the changes made to it in the meantime.
+    pub fn dispatch_optimistic<S: Optimistic>(
+        &self,
+        input: I,
+        state: &S,
+        update: impl FnOnce(&S),
+    ) -> impl Future<Output = ()> {
+        let rollback = Rollback {
+            state: state.clone(),
+            snapshot: Some(state.snapshot()),
+        };
+        update(state);
+        self.run(input, move |result| {
+            if result.is_ok() {
+                rollback.keep();
+            }
+        })
+    }
+
+    fn run(
+        &self,
+        input: I,
+        settle: impl FnOnce(&Result<O, E>) + 'static,
+    ) -> impl Future<Output = ()> {
+        let inner = Rc::clone(&self.inner);
+        let version = inner.version.get() + 1;
+        inner.version.set(version);
+        inner.error.set(None);
+        inner.running.replace_with(|running| *running + 1);
+        let running = Running(inner.running.clone());
+        let future = (inner.handler)(input);
+        async move {
+            let _running = running;
+            let result = future.await;
+            settle(&result);
+            if inner.version.get() == version {
+                match result {
+                    Ok(value) => inner.value.set(Some(value)),
+                    Err(error) => inner.error.set(Some(error)),
+                }
+            }
+        }
+    }
+
+    /// Whether a dispatch is still running.
+    pub fn pending(&self) -> impl Signal<Item = bool> {
+        self.inner.running.signal_ref(|running| *running > 0)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.inner.running.get() > 0
+    }
+
+    /// The value of the latest successful dispatch.
+    pub fn value(&self) -> impl Signal<Item = Option<O>> {
+        self.inner.value.signal_cloned()
+    }
+
+    /// The error of the latest dispatch, if it failed.
+    pub fn error(&self) -> impl Signal<Item = Option<E>> {
+        self.inner.error.signal_cloned()
+    }
+
+    pub fn get_value(&self) -> Option<O> {
+        self.inner.value.get_cloned()
+    }
+
+    pub fn get_error(&self) -> Option<E> {
+        self.inner.error.get_cloned()
+    }
+}
+
+impl<I: 'static, O: 'static, E: 'static, Ev> Callback<Ev> for Action<I, O, E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::Action;
+    use futures_signals::signal_vec::MutableVec;
+    use futures_util::{future, FutureExt};
+
+    #[test]
+    fn dispatch_sets_value_and_error() {
+        let action =
+            Action::new(|n: i32| future::ready(if n >= 0 { Ok(n * 2) } else { Err("negative") }));
+
+        let running = action.dispatch(2);
+        assert!(action.is_pending());
+        running.now_or_never().unwrap();
+        assert!(!action.is_pending());
+        assert_eq!(action.get_value(), Some(4));
+
+        action.dispatch(-1).now_or_never().unwrap();
+        assert_eq!(action.get_value(), Some(4));
+        assert_eq!(action.get_error(), Some("negative"));
+    }
+
+    #[test]
+    fn optimistic_update_rolled_back_on_failure() {
+        let todos = MutableVec::new_with_values(vec!["a"]);
+        let action = Action::new(|ok: bool| future::ready(if ok { Ok(()) } else { Err(()) }));
+
+        let add = |todos: &MutableVec<&str>| todos.lock_mut().push("b");
+        let running = action.dispatch_optimistic(true, &todos, add);
+        assert_eq!(*todos.lock_ref(), ["a", "b"]);
+        running.now_or_never().unwrap();
+        assert_eq!(*todos.lock_ref(), ["a", "b"]);
+
+        let remove = |todos: &MutableVec<&str>| todos.lock_mut().clear();
+        let running = action.dispatch_optimistic(false, &todos, remove);
+        assert!(todos.lock_ref().is_empty());
+        running.now_or_never().unwrap();
+        assert_eq!(*todos.lock_ref(), ["a", "b"]);
+    }
+
+    #[test]
+    fn optimistic_update_rolled_back_when_dropped() {
+        let todos = MutableVec::new_with_values(vec!["a"]);
+        let action = Action::new(|_: ()| future::pending::<Result<(), ()>>());
+
+        let running = action.dispatch_optimistic((), &todos, |todos| todos.lock_mut().push("b"));
+        assert_eq!(running.now_or_never(), None);
+        assert_eq!(*todos.lock_ref(), ["a"]);
+        assert!(!action.is_pending());
+
+        let running = action.dispatch_optimistic((), &todos, |todos| todos.lock_mut().push("c"));
+        assert_eq!(*todos.lock_ref(), ["a", "c"]);
+        drop(running);
+        assert_eq!(*todos.lock_ref(), ["a"]);
+    }
+}
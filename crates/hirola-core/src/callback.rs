@@ -1,4 +1,5 @@
 use futures_signals::{signal::Mutable, signal_vec::MutableVec};
+use std::future::Future;
 
 /// Allows a shorthand for creating event listeners.
 /// Mainly useful in event emitting nodes
@@ -26,6 +27,65 @@ pub trait Callback<E = ()>: Clone + 'static {
         };
         Box::new(cb)
     }
+    /// Pass an async callback that gets a clone of self and the event.
+    /// Each invocation spawns the returned future, see [`AsyncCallback`].
+    fn callback_async<F, Fut>(&self, f: F) -> AsyncCallback<Box<dyn Fn(E) -> Fut>>
+    where
+        F: Fn(Self, E) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let state = self.clone();
+        AsyncCallback::new(Box::new(move |e| f(state.clone(), e)))
+    }
+}
+
+/// What an [`AsyncCallback`] does when it is invoked while a previous invocation is still running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overlap {
+    /// Run the invocations alongside each other.
+    #[default]
+    Allow,
+    /// Skip the new invocation.
+    Ignore,
+    /// Cancel the running invocations and start the new one.
+    Cancel,
+}
+
+/// An event handler that returns a future, which the backend spawns on every event.
+///
+/// The futures belong to the node the handler is registered on, and are cancelled once the
+/// handler is removed. `html!` wraps async closures passed to `on:` in an `AsyncCallback`, so
+/// the following are equivalent:
+///
+/// ```rust,ignore
+/// html! { <button on:click=async move |_| save().await>"Save"</button> }
+/// html! { <button on:click=AsyncCallback::new(async move |_| save().await)>"Save"</button> }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncCallback<F> {
+    pub handler: F,
+    pub overlap: Overlap,
+}
+
+impl<F> AsyncCallback<F> {
+    pub fn new(handler: F) -> Self {
+        AsyncCallback {
+            handler,
+            overlap: Overlap::Allow,
+        }
+    }
+
+    /// Skips the events that happen while a previous one is still being handled.
+    pub fn ignore_overlapping(mut self) -> Self {
+        self.overlap = Overlap::Ignore;
+        self
+    }
+
+    /// Cancels the handling of the previous events when a new one happens.
+    pub fn cancel_overlapping(mut self) -> Self {
+        self.overlap = Overlap::Cancel;
+        self
+    }
 }
 
 impl<T: Clone + 'static, E> Callback<E> for Mutable<T> {}
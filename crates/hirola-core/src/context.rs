@@ -0,0 +1,47 @@
+//! Passing values down the tree without threading them through every component.
+//!
+//! Values are provided to an [`Owner`], and are seen by everything built inside that owner,
+//! including content that [`Match`](crate::templating::switch::Match),
+//! [`Suspense`](crate::templating::suspense::Suspense) and the other containers build later on.
+//!
+//! ```rust,ignore
+//! #[derive(Clone)]
+//! struct Theme(&'static str);
+//!
+//! fn button() -> Dom {
+//!     let theme = use_context::<Theme>().map_or("light", |theme| theme.0);
+//!     html! { <button class=theme>"Save"</button> }
+//! }
+//!
+//! with_context(Theme("dark"), || html! { <form>{button()}</form> })
+//! ```
+use crate::owner::Owner;
+
+/// Builds `f` in a new owner nested in the current one, with `value` provided to it.
+///
+/// Only what `f` builds sees `value`, and a value of the same type provided further up the tree
+/// is shadowed meanwhile. What `f` builds is mounted and removed along with the content it is
+/// built in.
+pub fn with_context<T: 'static, R>(value: T, f: impl FnOnce() -> R) -> R {
+    let owner = Owner::new(Some(&Owner::current_or_detached()));
+    owner.provide(value);
+    let content = owner.run(f);
+    owner.mount();
+    content
+}
+
+/// Provides `value` to the rest of the content of the current owner, replacing the value of the
+/// same type provided to it before.
+///
+/// Does nothing outside of an owner, eg. in a view that is rendered directly rather than from a
+/// container like [`Match`](crate::templating::switch::Match); use [`with_context`] there.
+pub fn provide_context<T: 'static>(value: T) {
+    if let Some(owner) = Owner::current() {
+        owner.provide(value);
+    }
+}
+
+/// The value of type `T` provided closest to the content being built.
+pub fn use_context<T: Clone + 'static>() -> Option<T> {
+    Owner::current()?.get()
+}
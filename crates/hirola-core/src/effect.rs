@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use std::pin::Pin;
+
 use futures_signals::signal::{DedupeMap, Mutable, Signal, SignalExt};
 
 use crate::prelude::GenericNode;
@@ -34,13 +36,101 @@ impl EffectAttribute for DefaultAttrStr {
     }
 }
 
+/// A value an attribute is bound to.
+///
+/// `bool` sets the attribute to an empty value when `true` and removes it when `false`, eg.
+/// `disabled=false`, and `Option` removes it when `None`. Other types are set through their
+/// [`Display`] output by wrapping them in [`Displayed`], or by implementing this trait.
+pub trait AttributeValue {
+    /// The value to set the attribute to, or `None` to remove it.
+    fn to_attribute(&self) -> Option<String>;
+}
+
+macro_rules! impl_attribute_value {
+    ($($value_type:ty),*) => {
+        $(
+            impl AttributeValue for $value_type {
+                fn to_attribute(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+            }
+
+            impl StyleValue for $value_type {
+                fn to_style(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_attribute_value!(&str, String, &String, char, f32, f64);
+impl_attribute_value!(usize, isize, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl AttributeValue for bool {
+    fn to_attribute(&self) -> Option<String> {
+        self.then(String::new)
+    }
+}
+
+impl<T: AttributeValue> AttributeValue for Option<T> {
+    fn to_attribute(&self) -> Option<String> {
+        self.as_ref().and_then(AttributeValue::to_attribute)
+    }
+}
+
+/// Sets an attribute to the [`Display`] output of a value, eg. an enum:
+///
+/// ```rust,ignore
+/// let theme = Mutable::new(Theme::Dark);
+/// html! { <main data-theme=theme.signal().map(Displayed)></main> }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Displayed<T>(pub T);
+
+impl<T: Display> AttributeValue for Displayed<T> {
+    fn to_attribute(&self) -> Option<String> {
+        Some(self.0.to_string())
+    }
+}
+
+/// A value an inline style property is set to by a `style:` directive.
+///
+/// Like [`AttributeValue`], `Option` removes the property when `None`. There is no `bool`: a
+/// property has no meaning of its own, so `style:color=true` does not compile.
+pub trait StyleValue {
+    /// The value to set the property to, or `None` to remove it.
+    fn to_style(&self) -> Option<String>;
+}
+
+impl<T: StyleValue> StyleValue for Option<T> {
+    fn to_style(&self) -> Option<String> {
+        self.as_ref().and_then(StyleValue::to_style)
+    }
+}
+
+impl<T: Display> StyleValue for Displayed<T> {
+    fn to_style(&self) -> Option<String> {
+        Some(self.0.to_string())
+    }
+}
+
+/// Sets the attribute `name` of `node` to `value`, or removes it.
+fn apply_attribute<Node: GenericNode>(node: &Node, name: &str, value: &impl AttributeValue) {
+    match value.to_attribute() {
+        Some(value) => node.set_attribute(name, &value),
+        None => node.remove_attribute(name),
+    }
+    .unwrap_or_else(|error| node.report_error(error))
+}
+
 macro_rules! impl_simple_effect {
     ($effect_type:ty) => {
         impl<Node: GenericNode> SideEffect<DefaultAttrStr, $effect_type, Node>
             for DefaultAttributeEffect
         {
             fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: $effect_type) {
-                node.set_attribute(attr.0, &effect.to_string())
+                apply_attribute(node, attr.0, &effect)
             }
         }
     };
@@ -62,6 +152,22 @@ impl_simple_effect!(u32);
 impl_simple_effect!(u64);
 impl_simple_effect!(u128);
 
+impl<Node: GenericNode, T: AttributeValue> SideEffect<DefaultAttrStr, Option<T>, Node>
+    for DefaultAttributeEffect
+{
+    fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: Option<T>) {
+        apply_attribute(node, attr.0, &effect)
+    }
+}
+
+impl<Node: GenericNode, T: Display> SideEffect<DefaultAttrStr, Displayed<T>, Node>
+    for DefaultAttributeEffect
+{
+    fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: Displayed<T>) {
+        apply_attribute(node, attr.0, &effect)
+    }
+}
+
 // macro_rules! impl_signal_effect {
 //     ($effect_type:ty) => {
 //         impl<Node: GenericNode, A: Display + 'static + Clone + PartialEq>
@@ -79,13 +185,13 @@ impl_simple_effect!(u128);
 //     };
 // }
 
-impl<Node: GenericNode, A: Display + 'static + Clone + PartialEq>
+impl<Node: GenericNode, A: AttributeValue + 'static + Clone + PartialEq>
     SideEffect<DefaultAttrStr, Mutable<A>, Node> for DefaultAttributeEffect
 {
     fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: Mutable<A>) {
         let dom = node.clone();
         let future = SignalExt::dedupe_map(effect.signal_cloned(), move |value| {
-            GenericNode::set_attribute(&dom, attr.0, &value.to_string());
+            apply_attribute(&dom, attr.0, &*value);
         })
         .to_future();
         node.effect(future);
@@ -96,7 +202,7 @@ impl<
         Node: GenericNode,
         F: FnMut(&mut <S as Signal>::Item) -> A + 'static,
         S: Signal + 'static,
-        A: Display + 'static + Clone + PartialEq,
+        A: AttributeValue + 'static + Clone + PartialEq,
     > SideEffect<DefaultAttrStr, DedupeMap<S, F>, Node> for DefaultAttributeEffect
 where
     <S as Signal>::Item: PartialEq,
@@ -104,7 +210,7 @@ where
     fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: DedupeMap<S, F>) {
         let dom = node.clone();
         let future = SignalExt::dedupe_map(effect, move |value| {
-            GenericNode::set_attribute(&dom, attr.0, &value.to_string());
+            apply_attribute(&dom, attr.0, &*value);
         })
         .to_future();
         node.effect(future);
@@ -113,15 +219,18 @@ where
 
 use futures_signals::signal::Dedupe;
 
-impl<Node: GenericNode, S: Signal<Item = A> + 'static, A: Display + 'static + Copy + PartialEq>
-    SideEffect<DefaultAttrStr, Dedupe<S>, Node> for DefaultAttributeEffect
+impl<
+        Node: GenericNode,
+        S: Signal<Item = A> + 'static,
+        A: AttributeValue + 'static + Copy + PartialEq,
+    > SideEffect<DefaultAttrStr, Dedupe<S>, Node> for DefaultAttributeEffect
 where
     <S as Signal>::Item: PartialEq,
 {
     fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: Dedupe<S>) {
         let dom = node.clone();
         let future = SignalExt::dedupe_map(effect, move |value| {
-            GenericNode::set_attribute(&dom, attr.0, &value.to_string());
+            apply_attribute(&dom, attr.0, &*value);
         })
         .to_future();
         node.effect(future);
@@ -133,7 +242,7 @@ use futures_signals::signal::DedupeCloned;
 impl<
         Node: GenericNode,
         S: Signal<Item = A> + 'static,
-        A: Display + 'static + Clone + PartialEq,
+        A: AttributeValue + 'static + Clone + PartialEq,
     > SideEffect<DefaultAttrStr, DedupeCloned<S>, Node> for DefaultAttributeEffect
 where
     <S as Signal>::Item: PartialEq,
@@ -141,7 +250,7 @@ where
     fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: DedupeCloned<S>) {
         let dom = node.clone();
         let future = SignalExt::dedupe_map(effect, move |value| {
-            GenericNode::set_attribute(&dom, attr.0, &value.to_string());
+            apply_attribute(&dom, attr.0, &*value);
         })
         .to_future();
         node.effect(future);
@@ -154,7 +263,7 @@ impl<
         Node: GenericNode,
         F: FnMut(<S as Signal>::Item) -> A + 'static,
         S: Signal + 'static,
-        A: Display + 'static + Clone + PartialEq,
+        A: AttributeValue + 'static + Clone + PartialEq,
     > SideEffect<DefaultAttrStr, Map<S, F>, Node> for DefaultAttributeEffect
 where
     <S as Signal>::Item: PartialEq,
@@ -162,9 +271,209 @@ where
     fn effect(&self, node: &Node, attr: DefaultAttrStr, effect: Map<S, F>) {
         let dom = node.clone();
         let future = SignalExt::map(effect, move |value| {
-            GenericNode::set_attribute(&dom, attr.0, &value.to_string());
+            apply_attribute(&dom, attr.0, &value);
         })
         .to_future();
         node.effect(future);
     }
 }
+
+/// Handles `class:name=value`, adding the class `name` while `value` is `true` and removing it
+/// otherwise, through [`GenericNode::toggle_class`]. `value` is a `bool`, a `Mutable<bool>` or a
+/// signal of `bool`, eg. one built with `map_ref!` or `signal_ref`.
+pub struct ClassEffect;
+
+/// The class toggled by a `class:` directive.
+pub struct ClassName(pub &'static str);
+
+impl EffectAttribute for ClassName {
+    type Handler = ClassEffect;
+    fn read_as_attr(&self) -> String {
+        self.0.to_owned()
+    }
+}
+
+fn toggle_class<Node: GenericNode>(node: &Node, name: &str, on: bool) {
+    node.toggle_class(name, on)
+        .unwrap_or_else(|error| node.report_error(error))
+}
+
+impl<Node: GenericNode> SideEffect<ClassName, bool, Node> for ClassEffect {
+    fn effect(&self, node: &Node, attr: ClassName, effect: bool) {
+        toggle_class(node, attr.0, effect)
+    }
+}
+
+impl<Node: GenericNode> SideEffect<ClassName, Mutable<bool>, Node> for ClassEffect {
+    fn effect(&self, node: &Node, attr: ClassName, effect: Mutable<bool>) {
+        self.effect(node, attr, effect.signal())
+    }
+}
+
+fn toggle_class_signal<Node: GenericNode>(
+    node: &Node,
+    name: &'static str,
+    signal: impl Signal<Item = bool> + 'static,
+) {
+    let dom = node.clone();
+    let future = signal.dedupe().for_each(move |on| {
+        toggle_class(&dom, name, on);
+        async {}
+    });
+    node.effect(future);
+}
+
+/// Implements `$effect` for the signals of `futures-signals` whose items match `$signal`, applying
+/// them with `$apply`. The impls are for every [`GenericNode`], or for the nodes of a backend
+/// when `$node` is given, eg. for the `prop:` directive.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_effect_for_signals {
+    ($effect:ident, $attr:ident, $signal:path, $apply:path) => {
+        $crate::impl_effect_for_signals!(@signals [generic] $effect, $attr, $signal, $apply);
+    };
+    ($effect:ident, $attr:ident, $signal:path, $apply:path, $node:ty) => {
+        $crate::impl_effect_for_signals!(@signals [$node] $effect, $attr, $signal, $apply);
+    };
+    (@signals $node:tt $effect:ident, $attr:ident, $signal:path, $apply:path) => {
+        $crate::impl_effect_for_signals! {
+            @impl $node $effect, $attr, $signal, $apply;
+            impl<A> for $crate::prelude::signal::MutableSignal<A>;
+            impl<A> for $crate::prelude::signal::MutableSignalCloned<A>;
+            impl<A, F> for $crate::prelude::signal::MutableSignalRef<A, F>;
+            impl<A, F> for $crate::prelude::signal::Map<A, F>;
+            impl<F> for $crate::prelude::internal::MapRefSignal<F>;
+            impl<A: $crate::prelude::signal::Signal> for $crate::prelude::signal::Dedupe<A>;
+            impl<A: $crate::prelude::signal::Signal> for $crate::prelude::signal::DedupeCloned<A>;
+            impl<A: $crate::prelude::signal::Signal, F> for $crate::prelude::signal::DedupeMap<A, F>;
+            impl<A: $crate::prelude::signal::Signal> for $crate::prelude::signal::BroadcasterSignal<A>;
+            impl<A: $crate::prelude::signal::Signal>
+                for $crate::prelude::signal::BroadcasterSignalCloned<A>;
+            impl<A: $crate::prelude::signal::Signal, F>
+                for $crate::prelude::signal::BroadcasterSignalRef<A, F>;
+        }
+    };
+    (@impl [generic] $effect:ident, $attr:ident, $signal:path, $apply:path;
+        $(impl<$($param:ident $(: $bound:path)?),*> for $ty:ty;)*) => {
+        $(
+            impl<$($param $(: $bound)?,)* Node: $crate::generic_node::GenericNode>
+                $crate::effect::SideEffect<$attr, $ty, Node> for $effect
+            where
+                $ty: $signal + 'static,
+            {
+                fn effect(&self, node: &Node, attr: $attr, effect: $ty) {
+                    $apply(node, attr.0, effect)
+                }
+            }
+        )*
+    };
+    (@impl [$node:ty] $effect:ident, $attr:ident, $signal:path, $apply:path;
+        $(impl<$($param:ident $(: $bound:path)?),*> for $ty:ty;)*) => {
+        $(
+            impl<$($param $(: $bound)?),*> $crate::effect::SideEffect<$attr, $ty, $node> for $effect
+            where
+                $ty: $signal + 'static,
+            {
+                fn effect(&self, node: &$node, attr: $attr, effect: $ty) {
+                    $apply(node, attr.0, effect)
+                }
+            }
+        )*
+    };
+}
+
+impl_effect_for_signals!(
+    ClassEffect,
+    ClassName,
+    Signal<Item = bool>,
+    toggle_class_signal
+);
+
+/// Toggles the class with a boxed signal, eg. one returned by [`SignalExt::boxed_local`]
+impl<Node: GenericNode> SideEffect<ClassName, Pin<Box<dyn Signal<Item = bool>>>, Node>
+    for ClassEffect
+{
+    fn effect(&self, node: &Node, attr: ClassName, effect: Pin<Box<dyn Signal<Item = bool>>>) {
+        toggle_class_signal(node, attr.0, effect)
+    }
+}
+
+/// Handles `style:property=value`, setting the inline style `property` to `value` through
+/// [`GenericNode::set_style_property`]. `value` is a [`StyleValue`], a `Mutable` of one or a
+/// signal of one, like the `value` of a [`ClassEffect`]. The property is removed when `value` is
+/// `None`.
+pub struct StyleEffect;
+
+/// The inline style property set by a `style:` directive.
+pub struct StyleProperty(pub &'static str);
+
+impl EffectAttribute for StyleProperty {
+    type Handler = StyleEffect;
+    fn read_as_attr(&self) -> String {
+        self.0.to_owned()
+    }
+}
+
+fn set_style_property<Node: GenericNode>(node: &Node, property: &str, value: &impl StyleValue) {
+    node.set_style_property(property, value.to_style().as_deref())
+        .unwrap_or_else(|error| node.report_error(error))
+}
+
+impl<Node: GenericNode, T: StyleValue> SideEffect<StyleProperty, T, Node> for StyleEffect {
+    fn effect(&self, node: &Node, attr: StyleProperty, effect: T) {
+        set_style_property(node, attr.0, &effect)
+    }
+}
+
+impl<Node: GenericNode, A: StyleValue + Clone + PartialEq + 'static>
+    SideEffect<StyleProperty, Mutable<A>, Node> for StyleEffect
+{
+    fn effect(&self, node: &Node, attr: StyleProperty, effect: Mutable<A>) {
+        self.effect(node, attr, effect.signal_cloned())
+    }
+}
+
+fn set_style_signal<Node: GenericNode>(
+    node: &Node,
+    property: &'static str,
+    signal: impl Signal<Item: StyleValue + Clone + PartialEq> + 'static,
+) {
+    let dom = node.clone();
+    let future = signal.dedupe_cloned().for_each(move |value| {
+        set_style_property(&dom, property, &value);
+        async {}
+    });
+    node.effect(future);
+}
+
+impl_effect_for_signals!(
+    StyleEffect,
+    StyleProperty,
+    Signal<Item: StyleValue + Clone + PartialEq + 'static>,
+    set_style_signal
+);
+
+/// Sets the property with a boxed signal, eg. one returned by [`SignalExt::boxed_local`]
+impl<Node: GenericNode, A: StyleValue + Clone + PartialEq + 'static>
+    SideEffect<StyleProperty, Pin<Box<dyn Signal<Item = A>>>, Node> for StyleEffect
+{
+    fn effect(&self, node: &Node, attr: StyleProperty, effect: Pin<Box<dyn Signal<Item = A>>>) {
+        set_style_signal(node, attr.0, effect)
+    }
+}
+
+/// Handles `prop:name=value`, setting the property `name` of the element instead of its
+/// attribute, eg. `prop:indeterminate=true`. Each backend implements it for its nodes:
+/// `hirola-dom` sets the JavaScript property, and `hirola-ssr` renders the attribute the property
+/// reflects, if any.
+pub struct PropEffect;
+
+/// The property set by a `prop:` directive.
+pub struct Property(pub &'static str);
+
+impl EffectAttribute for Property {
+    type Handler = PropEffect;
+    fn read_as_attr(&self) -> String {
+        self.0.to_owned()
+    }
+}
@@ -1,6 +1,6 @@
 use std::{cell::RefCell, future::Future};
 
-use crate::prelude::Render;
+use crate::prelude::{Error, Render};
 
 pub trait GenericNode: std::fmt::Debug + Clone + PartialEq + std::cmp::Eq + 'static {
     /// Create a new element node.
@@ -18,23 +18,38 @@ pub trait GenericNode: std::fmt::Debug + Clone + PartialEq + std::cmp::Eq + 'sta
     fn marker() -> Self;
 
     /// Sets an attribute on a node.
-    fn set_attribute(&self, name: &str, value: &str);
+    fn set_attribute(&self, name: &str, value: &str) -> Result<(), Error>;
+
+    /// Removes an attribute from a node. Removing an attribute the node does not have does nothing.
+    fn remove_attribute(&self, name: &str) -> Result<(), Error>;
+
+    /// Adds the class `name` to an element when `on` is `true`, and removes it otherwise.
+    /// The other classes of the element are left as they are.
+    fn toggle_class(&self, name: &str, on: bool) -> Result<(), Error>;
+
+    /// Sets the inline style `property` of an element, or removes it when `value` is `None`.
+    /// The other properties of the element are left as they are.
+    fn set_style_property(&self, property: &str, value: Option<&str>) -> Result<(), Error>;
 
     /// Appends a child to the node's children.
-    fn append_child(&self, child: &Self);
+    fn append_child(&self, child: &Self) -> Result<(), Error>;
 
     /// Insert a new child node to this node's children. If `reference_node` is `Some`, the child will be inserted
     /// before the reference node. Else if `None`, the child will be inserted at the end.
-    fn insert_child_before(&self, new_node: &Self, reference_node: Option<&Self>);
+    fn insert_child_before(
+        &self,
+        new_node: &Self,
+        reference_node: Option<&Self>,
+    ) -> Result<(), Error>;
 
     /// Remove a child node from this node's children.
-    fn remove_child(&self, child: &Self);
+    fn remove_child(&self, child: &Self) -> Result<(), Error>;
 
     /// Replace a child node from this node's children with a new child node.
-    fn replace_child(&self, old: &Self, new: &Self);
+    fn replace_child(&self, old: &Self, new: &Self) -> Result<(), Error>;
 
     /// Insert a new node before this node.
-    fn insert_sibling_before(&self, child: &Self);
+    fn insert_sibling_before(&self, child: &Self) -> Result<(), Error>;
 
     /// Returns the parent node, or `None` if detached.
     fn parent_node(&self) -> Option<Self>;
@@ -43,20 +58,48 @@ pub trait GenericNode: std::fmt::Debug + Clone + PartialEq + std::cmp::Eq + 'sta
     fn next_sibling(&self) -> Option<Self>;
 
     /// Remove this node from the tree.
-    fn remove_self(&self);
+    fn remove_self(&self) -> Result<(), Error>;
 
     /// Update inner text of the node. If the node has elements, all the elements are replaced with a new text node.
-    fn update_inner_text(&self, text: &str);
+    fn update_inner_text(&self, text: &str) -> Result<(), Error>;
 
     /// Replace all the children in a node with a new node
-    fn replace_children_with(&self, node: &Self);
+    fn replace_children_with(&self, node: &Self) -> Result<(), Error>;
 
     fn effect(&self, future: impl Future<Output = ()> + 'static);
 
+    /// Register a future that the content of this node is waiting on, eg. the one driving a [`Suspense`].
+    /// Unlike [`GenericNode::effect`], which may run for as long as the node lives, this future is expected
+    /// to complete. Renderers that wait for the tree to settle, such as async SSR, wait for it before finishing.
+    ///
+    /// [`Suspense`]: crate::templating::suspense::Suspense
+    fn suspense(&self, future: impl Future<Output = ()> + 'static) {
+        self.effect(future)
+    }
+
     fn children(&self) -> RefCell<Vec<Self>>;
 
-    fn append_render(&self, render: impl Render<Self> + 'static) {
-        Box::new(render).render_into(self).unwrap();
+    /// Handle an error that has no caller to be returned to, eg. one raised while an effect updates
+    /// this node.
+    fn report_error(&self, error: Error);
+
+    /// Keep an error raised while building this node, eg. by `html!`, until the node is rendered.
+    /// See [`GenericNode::append_built`].
+    fn keep_error(&self, error: Error);
+
+    /// Take the first error kept on this node or the nodes under it. The others are dropped.
+    fn take_error(&self) -> Option<Error>;
+
+    /// Appends a node built by `html!`, or returns the error it kept while being built.
+    fn append_built(&self, child: &Self) -> Result<(), Error> {
+        match child.take_error() {
+            Some(error) => Err(error),
+            None => self.append_child(child),
+        }
+    }
+
+    fn append_render(&self, render: impl Render<Self> + 'static) -> Result<(), Error> {
+        Box::new(render).render_into(self)
     }
 }
 
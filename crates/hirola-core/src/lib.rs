@@ -29,22 +29,34 @@
 use std::{future::Future, pin::Pin};
 pub type BoxedLocal<T> = Pin<Box<dyn Future<Output = T> + 'static>>;
 
+pub mod action;
 pub mod callback;
+pub mod context;
 pub mod effect;
 pub mod generic_node;
+pub mod owner;
 pub mod render;
+pub mod resource;
 pub mod templating;
 
 pub mod prelude {
-    pub use crate::callback::Callback;
+    pub use crate::action::{Action, Optimistic};
+    pub use crate::callback::{AsyncCallback, Callback, Overlap};
+    pub use crate::context::{provide_context, use_context, with_context};
     pub use crate::effect::*;
     pub use crate::generic_node::EventListener;
     pub use crate::generic_node::GenericNode;
     pub use crate::generic_node::*;
+    pub use crate::owner::{on_cleanup, on_mount};
     pub use crate::render::*;
-    pub use crate::templating::flow::{Indexed, IndexedProps};
-    pub use crate::templating::suspense::{Suspend, Suspense, SuspenseResult::*};
-    pub use crate::templating::switch::Switch;
+    pub use crate::resource::Resource;
+    pub use crate::style;
+    pub use crate::templating::error_boundary::{ErrorBoundary, Retry};
+    pub use crate::templating::flow::{Indexed, IndexedProps, Keyed, KeyedProps};
+    pub use crate::templating::portal::{Portal, PortalRoot};
+    pub use crate::templating::styled::{Style, Styled};
+    pub use crate::templating::suspense::{Suspend, Suspense, SuspenseResult::*, TrySuspend};
+    pub use crate::templating::switch::{Match, MatchArms, Switch};
     pub use crate::BoxedLocal;
     pub use futures_signals::signal::Mutable;
     pub use futures_signals::signal_map::MutableBTreeMap;
@@ -0,0 +1,237 @@
+//! Ownership of the content rendered by components that replace or remove it over time.
+//!
+//! Components like [`Match`](crate::templating::switch::Match), [`Indexed`](crate::templating::flow::Indexed)
+//! and [`Suspense`](crate::templating::suspense::Suspense) build each piece of content they render
+//! inside a new [`Owner`], and dispose that owner once the content is removed. Cleanups registered
+//! while the content was built, eg. by a [`Portal`](crate::templating::portal::Portal), run then.
+//! Components hook into this with [`on_mount`] and [`on_cleanup`].
+//!
+//! ```rust,ignore
+//! fn clock() -> Dom {
+//!     let now = Mutable::new(0);
+//!     let ticks = now.clone();
+//!     on_mount(move || {
+//!         let interval = start_interval(move || *ticks.lock_mut() += 1);
+//!         on_cleanup(move || interval.cancel());
+//!     });
+//!     html! { <span>{now}</span> }
+//! }
+//! ```
+//!
+//! Owners also carry the values provided with the [`context`](crate::context) functions.
+use std::{
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    rc::{Rc, Weak},
+};
+
+thread_local! {
+    static CURRENT: RefCell<Option<Owner>> = const { RefCell::new(None) };
+    /// Owns the content built outside of any owner, until the entry point rendering it takes it.
+    static DETACHED: RefCell<Option<Owner>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct OwnerInner {
+    parent: Option<Weak<OwnerInner>>,
+    contexts: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+    mounts: RefCell<Vec<Box<dyn FnOnce()>>>,
+    cleanups: RefCell<Vec<Box<dyn FnOnce()>>>,
+    children: RefCell<Vec<Owner>>,
+    /// Set once the content was inserted by its container, it is mounted along with its parent.
+    inserted: Cell<bool>,
+    mounted: Cell<bool>,
+    disposed: Cell<bool>,
+}
+
+/// Owns the cleanups of some rendered content, and the owners of content nested in it.
+#[derive(Clone, Default)]
+pub struct Owner(Rc<OwnerInner>);
+
+impl fmt::Debug for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Owner")
+            .field("cleanups", &self.0.cleanups.borrow().len())
+            .field("mounted", &self.0.mounted.get())
+            .field("disposed", &self.0.disposed.get())
+            .finish()
+    }
+}
+
+/// Restores the previous owner, even when unwinding.
+struct Restore(Option<Owner>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+impl Owner {
+    /// Creates an owner that is disposed together with `parent`, which keeps it until then.
+    pub fn new(parent: Option<&Owner>) -> Self {
+        let owner = Owner(Rc::new(OwnerInner {
+            parent: parent.map(|parent| Rc::downgrade(&parent.0)),
+            ..Default::default()
+        }));
+        if let Some(parent) = parent {
+            parent.0.children.borrow_mut().push(owner.clone());
+        }
+        owner
+    }
+
+    fn parent(&self) -> Option<Owner> {
+        self.0.parent.as_ref()?.upgrade().map(Owner)
+    }
+
+    /// The owner of the content being built, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// The owner of the content being built, or the one of the content built outside of any
+    /// owner, see [`Owner::take_detached`].
+    pub(crate) fn current_or_detached() -> Self {
+        Owner::current().unwrap_or_else(|| {
+            DETACHED.with(|detached| {
+                detached
+                    .borrow_mut()
+                    .get_or_insert_with(Owner::default)
+                    .clone()
+            })
+        })
+    }
+
+    /// Takes the owner of the content built outside of any owner so far, eg. the page passed to
+    /// `render_to`. The entry point rendering that content mounts it once the content is inserted,
+    /// and disposes it once the content is removed.
+    pub fn take_detached() -> Self {
+        DETACHED
+            .with(|detached| detached.borrow_mut().take())
+            .unwrap_or_default()
+    }
+
+    /// Runs `f` with this as the current owner.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        let _restore = Restore(previous);
+        f()
+    }
+
+    /// Registers `f` to run when this owner is mounted.
+    /// Runs it right away if the owner is already mounted, and never once it is disposed.
+    pub fn on_mount(&self, f: impl FnOnce() + 'static) {
+        if self.0.mounted.get() {
+            self.run(f);
+        } else if !self.0.disposed.get() {
+            self.0.mounts.borrow_mut().push(Box::new(f));
+        }
+    }
+
+    /// Marks the content of this owner as inserted into its container. The owner is mounted right
+    /// away if the owner it is nested in is mounted, or else along with it.
+    ///
+    /// Mounting an owner mounts the inserted owners nested in it first, then runs its
+    /// [`on_mount`](Self::on_mount) callbacks in the order they were registered in.
+    pub fn mount(&self) {
+        self.0.inserted.set(true);
+        let parent_mounted = self.parent().as_ref().is_none_or(Owner::is_mounted);
+        if !parent_mounted || self.0.disposed.get() || self.0.mounted.replace(true) {
+            return;
+        }
+        let children = self.0.children.borrow().clone();
+        for child in children {
+            if child.0.inserted.get() {
+                child.mount();
+            }
+        }
+        let mounts = self.0.mounts.take();
+        for f in mounts {
+            self.run(f);
+        }
+    }
+
+    pub fn is_mounted(&self) -> bool {
+        self.0.mounted.get()
+    }
+
+    /// Registers `f` to run when this owner is disposed.
+    /// Runs it right away if the owner was already disposed.
+    pub fn on_cleanup(&self, f: impl FnOnce() + 'static) {
+        if self.0.disposed.get() {
+            f();
+        } else {
+            self.0.cleanups.borrow_mut().push(Box::new(f));
+        }
+    }
+
+    /// Disposes the owners nested in this one, then runs the cleanups of this owner in the
+    /// reverse order they were registered in. Only the first call has an effect.
+    pub fn dispose(&self) {
+        if self.0.disposed.replace(true) {
+            return;
+        }
+        if let Some(parent) = self.parent() {
+            parent
+                .0
+                .children
+                .borrow_mut()
+                .retain(|child| !Rc::ptr_eq(&child.0, &self.0));
+        }
+        for child in self.0.children.take() {
+            child.dispose();
+        }
+        self.0.mounts.take();
+        let cleanups = self.0.cleanups.take();
+        for cleanup in cleanups.into_iter().rev() {
+            cleanup();
+        }
+    }
+
+    /// Provides `value` to this owner and the owners nested in it, see
+    /// [`provide_context`](crate::context::provide_context).
+    pub fn provide<T: 'static>(&self, value: T) {
+        self.0
+            .contexts
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(value));
+    }
+
+    /// The value of type `T` provided to this owner or the closest owner it is nested in.
+    pub fn get<T: Clone + 'static>(&self) -> Option<T> {
+        let mut owner = Some(self.clone());
+        while let Some(current) = owner {
+            if let Some(value) = current.0.contexts.borrow().get(&TypeId::of::<T>()) {
+                return value.downcast_ref::<T>().cloned();
+            }
+            owner = current.parent();
+        }
+        None
+    }
+
+    pub fn is_disposed(&self) -> bool {
+        self.0.disposed.get()
+    }
+}
+
+/// Runs `f` once the content being built is mounted, ie. inserted by the container that owns it,
+/// eg. a [`Match`](crate::templating::switch::Match) arm once it is shown. `f` runs in the owner
+/// of the content, so it can register [`on_cleanup`] callbacks.
+///
+/// Outside of an owner, `f` runs once the entry point rendering the content, eg. `render_to`, has
+/// inserted it. See [`Owner::take_detached`].
+pub fn on_mount(f: impl FnOnce() + 'static) {
+    Owner::current_or_detached().on_mount(f)
+}
+
+/// Runs `f` once the content being built is removed, eg. when a
+/// [`Match`](crate::templating::switch::Match) switches to another arm, an
+/// [`Indexed`](crate::templating::flow::Indexed) row is removed or the router leaves a page.
+///
+/// Outside of an owner, `f` runs once the entry point that rendered the content removes it, eg.
+/// when the node returned by `render_to` is discarded.
+pub fn on_cleanup(f: impl FnOnce() + 'static) {
+    Owner::current_or_detached().on_cleanup(f)
+}
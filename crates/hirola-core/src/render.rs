@@ -1,23 +1,114 @@
 //! Trait for describing how components and other custom types should be rendered into DOM nodes.
 use crate::{
     generic_node::GenericNode,
+    owner::Owner,
     templating::flow::{Indexed, IndexedProps},
 };
 use futures_signals::{
-    signal::{Mutable, ReadOnlyMutable, SignalExt},
+    internal::MapRefSignal,
+    signal::{
+        Broadcaster, BroadcasterSignal, BroadcasterSignalCloned, BroadcasterSignalRef, Dedupe,
+        DedupeCloned, DedupeMap, Map, Mutable, MutableSignal, MutableSignalCloned,
+        MutableSignalRef, ReadOnlyMutable, Signal, SignalExt,
+    },
     signal_vec::{Filter, MutableSignalVec, MutableVec, SignalVec, SignalVecExt},
 };
+use futures_util::task::noop_waker_ref;
 use std::{
     fmt::{Debug, Display},
     iter::Enumerate,
     pin::Pin,
+    task::{Context, Poll},
 };
 
+/// A boxed error, used as the source of an [`Error`].
+pub type BoxedError = Box<dyn std::error::Error + 'static>;
+
+/// Errors raised while rendering nodes.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
+    /// The backend rejected an operation on a node, eg. removing a node that is not a child.
+    InvalidOperation {
+        /// The [`GenericNode`] operation, eg. `"remove_child"`.
+        operation: &'static str,
+        /// The tag of the node the operation was performed on, if it is an element.
+        tag: Option<String>,
+        source: Option<BoxedError>,
+    },
+    /// An attribute could not be set.
+    InvalidAttribute {
+        /// The tag of the node, if it is an element.
+        tag: Option<String>,
+        name: String,
+        source: Option<BoxedError>,
+    },
+    /// A global the backend relies on, like `window` or `document`, is not available.
+    Missing(&'static str),
+    /// No node is registered as the portal root a
+    /// [`Portal`](crate::templating::portal::Portal) renders into, with its name.
+    MissingPortalRoot(String),
+    /// A node is not of the type an operation expected.
+    FailedCast {
+        expected: &'static str,
+        /// The tag of the node, if it is an element.
+        tag: Option<String>,
+    },
+    /// The backend does not support the operation.
+    Unsupported {
+        backend: &'static str,
+        operation: &'static str,
+    },
+    /// A panic caught by an [`ErrorBoundary`](crate::templating::error_boundary::ErrorBoundary),
+    /// with its message.
+    Panicked(String),
+    /// Any other error raised by a backend.
     DomError(Box<dyn Debug>),
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn on(tag: &Option<String>) -> String {
+            tag.as_ref()
+                .map(|tag| format!(" on <{tag}>"))
+                .unwrap_or_default()
+        }
+        match self {
+            Error::InvalidOperation { operation, tag, .. } => {
+                write!(f, "invalid node operation `{operation}`{}", on(tag))
+            }
+            Error::InvalidAttribute { tag, name, .. } => {
+                write!(f, "could not set attribute `{name}`{}", on(tag))
+            }
+            Error::Missing(global) => write!(f, "could not acquire {global}"),
+            Error::MissingPortalRoot(name) => write!(f, "no portal root is registered as `{name}`"),
+            Error::FailedCast { expected, tag } => {
+                write!(f, "expected a node of type `{expected}`")?;
+                match tag {
+                    Some(tag) => write!(f, ", found <{tag}>"),
+                    None => Ok(()),
+                }
+            }
+            Error::Unsupported { backend, operation } => {
+                write!(f, "`{operation}` is not supported by the {backend} backend")
+            }
+            Error::Panicked(message) => write!(f, "panicked: {message}"),
+            Error::DomError(error) => write!(f, "{error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidOperation { source, .. } | Error::InvalidAttribute { source, .. } => {
+                source.as_deref()
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Trait for describing how something should be rendered into nodes.
 pub trait Render<N: GenericNode> {
     /// Called during the initial render when creating the nodes inside a dom.
@@ -34,24 +125,21 @@ impl<N: GenericNode> Render<N> for () {
 impl<N: GenericNode> Render<N> for &str {
     fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
         let child = &N::text_node(*self);
-        parent.append_child(child);
-        Ok(())
+        parent.append_child(child)
     }
 }
 
 impl<N: GenericNode> Render<N> for String {
     fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
         let child = &N::text_node(&self);
-        parent.append_child(child);
-        Ok(())
+        parent.append_child(child)
     }
 }
 
 impl<N: GenericNode> Render<N> for &String {
     fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
         let child = &N::text_node(&self);
-        parent.append_child(child);
-        Ok(())
+        parent.append_child(child)
     }
 }
 
@@ -101,13 +189,164 @@ impl<T: Render<N>, N: GenericNode> Render<N> for Box<T> {
 impl<T: Display + Clone + 'static, N: GenericNode> Render<N> for Mutable<T> {
     fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
         let child = N::text_node(&self.get_cloned().to_string());
-        parent.append_child(&child);
-        let fut = self.signal_ref(move |e| child.update_inner_text(&e.to_string()));
+        parent.append_child(&child)?;
+        let fut = self.signal_ref(move |e| {
+            child
+                .update_inner_text(&e.to_string())
+                .unwrap_or_else(|error| child.report_error(error))
+        });
+        parent.effect(fut.to_future());
+        Ok(())
+    }
+}
+
+macro_rules! impl_render_for_display {
+    ($($ty:ty),*) => {
+        $(
+            impl<N: GenericNode> Render<N> for $ty {
+                fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+                    let child = &N::text_node(&self.to_string());
+                    parent.append_child(child)
+                }
+            }
+        )*
+    };
+}
+
+impl_render_for_display!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+/// Renders any [`Display`] value as text, eg. a signal of a user type with
+/// `{status.signal_cloned().map(DisplayText)}`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayText<T>(pub T);
+
+impl<T: Display, N: GenericNode> Render<N> for DisplayText<T> {
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        parent.append_child(&N::text_node(&self.0.to_string()))
+    }
+}
+
+impl<T: Display + Clone + 'static, N: GenericNode> Render<N> for ReadOnlyMutable<T> {
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        let child = N::text_node(&self.get_cloned().to_string());
+        parent.append_child(&child)?;
+        let fut = self.signal_ref(move |e| {
+            child
+                .update_inner_text(&e.to_string())
+                .unwrap_or_else(|error| child.report_error(error))
+        });
         parent.effect(fut.to_future());
         Ok(())
     }
 }
 
+/// Renders every value of `signal` in place of the previous one.
+///
+/// The values are rendered between two markers, and the nodes between them are replaced each time
+/// the signal changes. The first value is rendered right away if the signal has one ready.
+/// Values that only implement [`Display`] can be rendered by mapping them to [`DisplayText`].
+pub fn render_signal<S, N>(signal: S, parent: &N) -> Result<(), Error>
+where
+    S: Signal + 'static,
+    S::Item: Render<N>,
+    N: GenericNode,
+{
+    fn swap<T: Render<N>, N: GenericNode>(
+        start: &N,
+        end: &N,
+        value: T,
+        owner: &Owner,
+    ) -> Result<(), Error> {
+        let Some(holder) = end.parent_node() else {
+            return Ok(());
+        };
+        while let Some(node) = start.next_sibling() {
+            if &node == end {
+                break;
+            }
+            holder.remove_child(&node)?;
+        }
+        let fragment = N::fragment();
+        owner.run(|| Box::new(value).render_into(&fragment))?;
+        holder.insert_child_before(&fragment, Some(end))?;
+        owner.mount();
+        Ok(())
+    }
+
+    let start = N::marker();
+    let end = N::marker();
+    parent.append_child(&start)?;
+    parent.append_child(&end)?;
+    let parent_owner = Owner::current();
+    let mut current = Owner::new(parent_owner.as_ref());
+    let mut signal = Box::pin(signal);
+    let mut cx = Context::from_waker(noop_waker_ref());
+    if let Poll::Ready(Some(value)) = signal.as_mut().poll_change(&mut cx) {
+        let fragment = N::fragment();
+        current.run(|| Box::new(value).render_into(&fragment))?;
+        parent.insert_child_before(&fragment, Some(&end))?;
+        current.mount();
+    }
+    let fut = signal.for_each(move |value| {
+        current.dispose();
+        current = Owner::new(parent_owner.as_ref());
+        swap(&start, &end, value, &current).unwrap_or_else(|error| end.report_error(error));
+        async {}
+    });
+    parent.effect(fut);
+    Ok(())
+}
+
+macro_rules! impl_render_for_signal {
+    ($(impl<$($param:ident $(: $bound:path)?),*> for $ty:ty;)*) => {
+        $(
+            impl<$($param $(: $bound)?,)* N: GenericNode> Render<N> for $ty
+            where
+                $ty: Signal + 'static,
+                <$ty as Signal>::Item: Render<N>,
+            {
+                fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+                    render_signal(*self, parent)
+                }
+            }
+        )*
+    };
+}
+
+impl_render_for_signal! {
+    impl<A> for MutableSignal<A>;
+    impl<A> for MutableSignalCloned<A>;
+    impl<A, F> for MutableSignalRef<A, F>;
+    impl<A, F> for Map<A, F>;
+    impl<F> for MapRefSignal<F>;
+    impl<A: Signal> for Dedupe<A>;
+    impl<A: Signal> for DedupeCloned<A>;
+    impl<A: Signal, F> for DedupeMap<A, F>;
+    impl<A: Signal> for BroadcasterSignal<A>;
+    impl<A: Signal> for BroadcasterSignalCloned<A>;
+    impl<A: Signal, F> for BroadcasterSignalRef<A, F>;
+}
+
+/// Renders a boxed signal, eg. one returned by [`SignalExt::boxed_local`]
+impl<T: Render<N> + 'static, N: GenericNode> Render<N> for Pin<Box<dyn Signal<Item = T>>> {
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        render_signal(*self, parent)
+    }
+}
+
+/// Renders the values of the broadcasted signal
+impl<A, N: GenericNode> Render<N> for Broadcaster<A>
+where
+    A: Signal + 'static,
+    A::Item: Render<N> + Clone,
+{
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        render_signal(self.signal_cloned(), parent)
+    }
+}
+
 pub struct MappedVec<T, G: GenericNode> {
     pub iter: Pin<Box<dyn SignalVec<Item = T>>>,
     callback: Box<dyn Fn(T) -> G>,
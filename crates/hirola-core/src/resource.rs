@@ -0,0 +1,261 @@
+//! Data loaded asynchronously from a key, eg. the user whose id is in the current route.
+//!
+//! A [`Resource`] runs its loader with every key its source signal yields, and exposes the
+//! outcome as a [`SuspenseResult`]. Responses to keys that were replaced in the meantime are
+//! dropped, so a slow response never overwrites the data of a newer key.
+//!
+//! ```rust,ignore
+//! let id = router.current_params().map(|params| params.get("id").cloned());
+//! let user = Resource::new(id, fetch_user);
+//! html! {
+//!     <section>
+//!         {user.render(|state| match state {
+//!             Loading => html! { <p>"Loading"</p> },
+//!             Ready(user) => html! { <p>{user.name}</p> },
+//!             Failed(error) => html! { <p class="error">{error.to_string()}</p> },
+//!         })}
+//!     </section>
+//! }
+//! ```
+use crate::{
+    generic_node::GenericNode,
+    render::{Error, Render},
+    templating::{
+        suspense::{Suspense, SuspenseResult},
+        switch::Match,
+    },
+    BoxedLocal,
+};
+use futures_signals::signal::{Broadcaster, Mutable, Signal, SignalExt};
+use futures_util::future::{FutureExt, Shared};
+use std::{cell::RefCell, collections::HashMap, future::Future, hash::Hash, pin::Pin, rc::Rc};
+
+type Source<K> = Pin<Box<dyn Signal<Item = K>>>;
+type Loader<K, T, E> = Box<dyn Fn(K) -> BoxedLocal<Result<T, E>>>;
+type Load<T, E> = Shared<BoxedLocal<Result<T, E>>>;
+
+struct Inner<K, T, E> {
+    state: Mutable<SuspenseResult<T, E>>,
+    /// Shared by the renders, which all listen to it.
+    source: Broadcaster<Source<K>>,
+    loader: Loader<K, T, E>,
+    /// The key of the latest load.
+    key: RefCell<Option<K>>,
+    /// Bumped on every load and mutation, responses of older loads are dropped. The renders show
+    /// every version once.
+    version: Mutable<usize>,
+    /// The load of the current version while it is running. The renders all wait on it, so it
+    /// keeps running as long as one of them is shown.
+    running: RefCell<Option<Load<T, E>>>,
+    /// The value loaded for every key, if the resource is [cached](Resource::cached).
+    cache: RefCell<Option<HashMap<K, T>>>,
+}
+
+impl<K, T, E> Inner<K, T, E>
+where
+    K: Clone + Eq + Hash + 'static,
+    T: Clone + 'static,
+    E: Clone + 'static,
+{
+    /// Loads `key`, from the cache unless `force` is set. The future returned by the loader is
+    /// polled once a render waits on it.
+    fn load(&self, key: K, force: bool) {
+        *self.key.borrow_mut() = Some(key.clone());
+        let cached = match &*self.cache.borrow() {
+            Some(cache) if !force => cache.get(&key).cloned(),
+            _ => None,
+        };
+        match cached {
+            Some(value) => {
+                *self.running.borrow_mut() = None;
+                self.state.set(SuspenseResult::Ready(value));
+            }
+            None => {
+                *self.running.borrow_mut() = Some((self.loader)(key).shared());
+                self.state.set(SuspenseResult::Loading);
+            }
+        }
+        *self.version.lock_mut() += 1;
+    }
+
+    /// Waits on `load`, and records its outcome unless a newer version replaced it.
+    async fn settle(self: Rc<Self>, version: usize, load: Load<T, E>) -> SuspenseResult<T, E> {
+        let state = load
+            .await
+            .map_or_else(SuspenseResult::Failed, SuspenseResult::Ready);
+        // The first render done with the load records it
+        if self.version.get() == version && self.running.borrow_mut().take().is_some() {
+            let key = self.key.borrow().clone();
+            if let (SuspenseResult::Ready(value), Some(cache), Some(key)) =
+                (&state, self.cache.borrow_mut().as_mut(), key)
+            {
+                cache.insert(key, value.clone());
+            }
+            self.state.set(state.clone());
+        }
+        state
+    }
+
+    /// Loads `key` yielded by the source, unless it is already the current key, eg. when another
+    /// render received it first.
+    fn receive(&self, key: K) {
+        if self.key.borrow().as_ref() != Some(&key) {
+            self.load(key, false);
+        }
+    }
+}
+
+/// Data loaded by an async `loader` for every key yielded by a `source` signal.
+///
+/// Loading starts once the resource is [rendered](Resource::render), and a load only runs while
+/// the content it is rendered in is shown: it is waited on by a [`Suspense`], like any other
+/// future. With async SSR, the page is only rendered once the loads have completed.
+pub struct Resource<K, T, E> {
+    inner: Rc<Inner<K, T, E>>,
+}
+
+impl<K, T, E> Clone for Resource<K, T, E> {
+    fn clone(&self) -> Self {
+        Resource {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+impl<K, T, E> Resource<K, T, E>
+where
+    K: Clone + Eq + Hash + 'static,
+    T: Clone + 'static,
+    E: Clone + 'static,
+{
+    pub fn new<S, F, Fut>(source: S, loader: F) -> Self
+    where
+        S: Signal<Item = K> + 'static,
+        F: Fn(K) -> Fut + 'static,
+        Fut: Future<Output = Result<T, E>> + 'static,
+    {
+        Resource {
+            inner: Rc::new(Inner {
+                state: Mutable::new(SuspenseResult::Loading),
+                source: Broadcaster::new(Box::pin(source)),
+                loader: Box::new(move |key| Box::pin(loader(key))),
+                key: RefCell::new(None),
+                version: Mutable::new(0),
+                running: RefCell::new(None),
+                cache: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// Keeps the value loaded for every key, so that switching back to a key shows it right away
+    /// instead of loading it again. [`refetch`](Self::refetch) still loads the current key.
+    pub fn cached(self) -> Self {
+        self.inner
+            .cache
+            .borrow_mut()
+            .get_or_insert_with(HashMap::new);
+        self
+    }
+
+    /// The state of the latest load.
+    pub fn get(&self) -> SuspenseResult<T, E> {
+        self.inner.state.get_cloned()
+    }
+
+    pub fn signal(&self) -> impl Signal<Item = SuspenseResult<T, E>> {
+        self.inner.state.signal_cloned()
+    }
+
+    /// Whether the latest load is still running.
+    pub fn loading(&self) -> impl Signal<Item = bool> {
+        self.inner
+            .state
+            .signal_ref(|state| matches!(state, SuspenseResult::Loading))
+    }
+
+    /// Loads the current key again, bypassing the cache.
+    pub fn refetch(&self) {
+        let key = self.inner.key.borrow().clone();
+        if let Some(key) = key {
+            self.inner.load(key, true);
+        }
+    }
+
+    /// Replaces the loaded value, eg. with the response of a mutation, without calling the loader.
+    /// A load that is still running is dropped.
+    pub fn mutate(&self, value: T) {
+        *self.inner.running.borrow_mut() = None;
+        let key = self.inner.key.borrow().clone();
+        if let (Some(cache), Some(key)) = (self.inner.cache.borrow_mut().as_mut(), key) {
+            cache.insert(key, value.clone());
+        }
+        self.inner.state.set(SuspenseResult::Ready(value));
+        *self.inner.version.lock_mut() += 1;
+    }
+
+    /// Renders the state of the resource with `template`, which runs again whenever it changes.
+    /// While a load is running, `template` renders [`SuspenseResult::Loading`] as the fallback of
+    /// a [`Suspense`], then its outcome.
+    pub fn render<G>(
+        &self,
+        template: impl Fn(SuspenseResult<T, E>) -> G + 'static,
+    ) -> ResourceView<K, T, E, G> {
+        ResourceView {
+            resource: self.clone(),
+            template: Box::new(template),
+        }
+    }
+}
+
+/// A [`Resource`] rendered with a template, see [`Resource::render`].
+pub struct ResourceView<K, T, E, G> {
+    resource: Resource<K, T, E>,
+    template: Box<dyn Fn(SuspenseResult<T, E>) -> G>,
+}
+
+impl<K, T, E, N> Render<N> for ResourceView<K, T, E, N>
+where
+    K: Clone + Eq + Hash + 'static,
+    T: Clone + 'static,
+    E: Clone + 'static,
+    N: GenericNode,
+{
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        let ResourceView { resource, template } = *self;
+        let inner = resource.inner;
+        let template: Rc<dyn Fn(SuspenseResult<T, E>) -> N> = Rc::from(template);
+
+        // Every version is rendered once, a running load through a `Suspense` waiting on it
+        let view = Rc::clone(&inner);
+        Box::new(Match::new(
+            inner.version.signal(),
+            |version: &usize| *version,
+            move |version| {
+                let running = view.running.borrow().clone();
+                let Some(load) = running else {
+                    return template(view.state.get_cloned());
+                };
+                let fallback = Rc::clone(&template);
+                let suspense = Suspense::new(Rc::clone(&view).settle(version, load), {
+                    let template = Rc::clone(&template);
+                    move |state| template(state)
+                })
+                .with_fallback(move || fallback(SuspenseResult::Loading));
+                let fragment = N::fragment();
+                if let Err(error) = fragment.append_render(suspense) {
+                    fragment.keep_error(error);
+                }
+                fragment
+            },
+        ))
+        .render_into(parent)?;
+
+        // Loads the current key, unless another render already did, then every new key
+        let keys = inner.source.signal_cloned();
+        parent.effect(keys.for_each(move |key| {
+            inner.receive(key);
+            async {}
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Error boundaries, which catch the errors raised in a subtree and render a fallback instead.
+//!
+//! Errors reach a boundary through [`GenericNode::report_error`]. Backends hand each reported
+//! error to [`catch`], and run effects and event handlers inside the [`ErrorScope`] they were
+//! registered in, using [`scoped`] and [`ErrorScope::run`].
+use crate::{
+    generic_node::GenericNode,
+    owner::Owner,
+    render::{Error, Render},
+};
+use futures_util::future::{self, Either};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    task::Poll,
+};
+
+type Handler = Rc<dyn Fn(Error)>;
+
+thread_local! {
+    static CURRENT: RefCell<ErrorScope> = RefCell::new(ErrorScope::default());
+}
+
+/// The boundaries that errors and panics raised at some point of the tree are sent to.
+///
+/// Capture it with [`ErrorScope::current`] when registering a callback, and [`run`](Self::run)
+/// the callback inside it, so that its errors reach the boundary it was registered under.
+#[derive(Clone, Default)]
+pub struct ErrorScope {
+    errors: Option<Handler>,
+    panics: Option<Handler>,
+}
+
+impl fmt::Debug for ErrorScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorScope")
+            .field("errors", &self.errors.is_some())
+            .field("panics", &self.panics.is_some())
+            .finish()
+    }
+}
+
+/// Restores the previous scope, even when unwinding.
+struct Restore(Option<ErrorScope>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+}
+
+impl ErrorScope {
+    /// The scope of the boundary being rendered, or of the callback being run.
+    pub fn current() -> Self {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Whether errors raised in this scope are caught by a boundary.
+    pub fn is_caught(&self) -> bool {
+        self.errors.is_some()
+    }
+
+    fn nested(&self, handler: Handler, catch_panics: bool) -> Self {
+        ErrorScope {
+            panics: if catch_panics {
+                Some(Rc::clone(&handler))
+            } else {
+                self.panics.clone()
+            },
+            errors: Some(handler),
+        }
+    }
+
+    /// Runs `f` inside this scope.
+    ///
+    /// If a boundary of this scope catches panics, a panic in `f` is sent to it and `None` is
+    /// returned. Catching panics relies on unwinding, so on targets that abort on panic, like
+    /// `wasm32-unknown-unknown` by default, the panic still aborts.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> Option<R> {
+        let previous = CURRENT.with(|current| current.replace(self.clone()));
+        let _restore = Restore(Some(previous));
+        match &self.panics {
+            None => Some(f()),
+            Some(handler) => match panic::catch_unwind(AssertUnwindSafe(f)) {
+                Ok(result) => Some(result),
+                Err(payload) => {
+                    handler(Error::Panicked(panic_message(payload)));
+                    None
+                }
+            },
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string()),
+    }
+}
+
+/// Sends `error` to the boundary of the current scope.
+/// Returns the error back if there is none, for the backend to handle.
+pub fn catch(error: Error) -> Result<(), Error> {
+    match ErrorScope::current().errors {
+        Some(handler) => {
+            handler(error);
+            Ok(())
+        }
+        None => Err(error),
+    }
+}
+
+/// Polls `future` inside the scope it was created in. A caught panic completes the future.
+pub fn scoped(future: impl Future<Output = ()> + 'static) -> impl Future<Output = ()> {
+    let scope = ErrorScope::current();
+    if !scope.is_caught() {
+        return Either::Left(future);
+    }
+    let mut future = Box::pin(future);
+    Either::Right(future::poll_fn(move |cx| {
+        scope
+            .run(|| future.as_mut().poll(cx))
+            .unwrap_or(Poll::Ready(()))
+    }))
+}
+
+/// Lets a fallback render the children of its [`ErrorBoundary`] again.
+#[derive(Clone)]
+pub struct Retry(Rc<dyn Fn()>);
+
+impl Retry {
+    /// Replaces the fallback with a fresh render of the children.
+    pub fn retry(&self) {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Retry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Retry").finish()
+    }
+}
+
+/// Renders `children`, or `fallback` with the first error raised in them.
+///
+/// Errors are caught while the children are built, eg. from nodes `html!` could not append, and
+/// afterwards from the effects and event handlers registered under them. Errors raised by
+/// `fallback` go to the enclosing boundary.
+///
+/// ```rust,ignore
+/// ErrorBoundary::new(
+///     || html! { <Chart /> },
+///     |error, retry| html! {
+///         <p>{error.to_string()}<button on:click=move |_| retry.retry()>"Retry"</button></p>
+///     },
+/// )
+/// ```
+pub struct ErrorBoundary<G> {
+    pub children: Box<dyn Fn() -> G>,
+    pub fallback: Box<dyn Fn(Error, Retry) -> G>,
+    /// Whether panics are caught too, see [`ErrorScope::run`].
+    pub panics: bool,
+}
+
+impl<G: 'static> ErrorBoundary<G> {
+    pub fn new(
+        children: impl Fn() -> G + 'static,
+        fallback: impl Fn(Error, Retry) -> G + 'static,
+    ) -> Self {
+        ErrorBoundary {
+            children: Box::new(children),
+            fallback: Box::new(fallback),
+            panics: false,
+        }
+    }
+
+    /// Catches panics as [`Error::Panicked`].
+    pub fn catch_panics(mut self) -> Self {
+        self.panics = true;
+        self
+    }
+}
+
+struct Boundary<N> {
+    holder: N,
+    marker: N,
+    /// The scope the boundary was rendered in, where its fallback and its own errors go.
+    outer: ErrorScope,
+    owner: Option<Owner>,
+    children: Box<dyn Fn() -> N>,
+    fallback: Box<dyn Fn(Error, Retry) -> N>,
+    panics: bool,
+    /// Bumped on every render, errors from older content are ignored.
+    generation: Cell<usize>,
+    /// Set while the children are being built, the first error raised meanwhile is kept in `caught`.
+    building: Cell<bool>,
+    caught: RefCell<Option<Error>>,
+    current: RefCell<Option<(N, Owner)>>,
+}
+
+impl<N: GenericNode> Boundary<N> {
+    fn render_children(self: &Rc<Self>) {
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+        // Held by the effects and event handlers registered under the children
+        let boundary = Rc::clone(self);
+        let handler: Handler = Rc::new(move |error| boundary.fail(generation, error));
+        let scope = self.outer.nested(handler, self.panics);
+
+        self.building.set(true);
+        let owner = Owner::new(self.owner.as_ref());
+        let content = scope.run(|| {
+            let frag = N::fragment();
+            owner
+                .run(|| frag.append_built(&(self.children)()))
+                .map(|_| frag)
+        });
+        self.building.set(false);
+
+        match (self.caught.take(), content) {
+            (None, Some(Ok(frag))) => self.show(frag, owner),
+            (Some(error), _) | (None, Some(Err(error))) => {
+                owner.dispose();
+                self.render_fallback(error)
+            }
+            (None, None) => owner.dispose(),
+        }
+    }
+
+    fn fail(self: &Rc<Self>, generation: usize, error: Error) {
+        if generation != self.generation.get() {
+            return;
+        }
+        if self.building.get() {
+            self.caught.borrow_mut().get_or_insert(error);
+        } else {
+            self.render_fallback(error);
+        }
+    }
+
+    fn render_fallback(self: &Rc<Self>, error: Error) {
+        self.generation.set(self.generation.get() + 1);
+        let boundary = Rc::clone(self);
+        let retry = Retry(Rc::new(move || boundary.render_children()));
+        let owner = Owner::new(self.owner.as_ref());
+        let content = self.outer.run(|| {
+            let frag = N::fragment();
+            owner
+                .run(|| frag.append_built(&(self.fallback)(error, retry)))
+                .map(|_| frag)
+        });
+        match content {
+            Some(Ok(frag)) => self.show(frag, owner),
+            Some(Err(error)) => {
+                owner.dispose();
+                self.report(error)
+            }
+            None => owner.dispose(),
+        }
+    }
+
+    /// Replaces the current content with `frag`.
+    fn show(&self, frag: N, owner: Owner) {
+        let previous = self.current.borrow_mut().take();
+        let result = previous
+            .map_or(Ok(()), |(previous, owner)| {
+                owner.dispose();
+                previous
+                    .children()
+                    .take()
+                    .iter()
+                    .try_for_each(|child| self.holder.remove_child(child))
+            })
+            .and_then(|_| self.holder.insert_child_before(&frag, Some(&self.marker)));
+        *self.current.borrow_mut() = Some((frag, owner.clone()));
+        match result {
+            Ok(()) => owner.mount(),
+            Err(error) => self.report(error),
+        }
+    }
+
+    fn report(&self, error: Error) {
+        self.outer.run(|| self.marker.report_error(error));
+    }
+}
+
+impl<N: GenericNode> Render<N> for ErrorBoundary<N> {
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        let ErrorBoundary {
+            children,
+            fallback,
+            panics,
+        } = *self;
+        let marker = N::marker();
+        parent.append_child(&marker)?;
+        let boundary = Rc::new(Boundary {
+            holder: parent.clone(),
+            marker,
+            outer: ErrorScope::current(),
+            owner: Owner::current(),
+            children,
+            fallback,
+            panics,
+            generation: Cell::new(0),
+            building: Cell::new(false),
+            caught: RefCell::new(None),
+            current: RefCell::new(None),
+        });
+        boundary.render_children();
+        Ok(())
+    }
+}
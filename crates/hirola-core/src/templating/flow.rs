@@ -3,10 +3,14 @@
 //! Iteration can be either _"keyed"_ or _"non keyed"_.
 //! Use the [`Keyed`] and [`Indexed`] utility components respectively.
 use crate::generic_node::GenericNode;
+use crate::owner::Owner;
 use crate::render::{Error, Render};
 use futures_signals::signal_vec::{SignalVec, SignalVecExt, VecDiff};
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::future::ready;
+use std::hash::Hash;
+use std::marker::PhantomData;
 use std::rc::Rc;
 
 /// Props for [`Indexed`].
@@ -59,14 +63,25 @@ where
         let props = self.props;
         let template = props.template;
 
-        let iterable = SignalVecExt::map(props.iterable, template);
-
         let marker = N::marker();
 
+        let owner = Owner::current();
+        let row_marker = marker.clone();
+        let iterable = SignalVecExt::map(props.iterable, move |item| {
+            let content = Owner::new(owner.as_ref());
+            let dom = content.run(|| template(item));
+            if let Some(error) = dom.take_error() {
+                row_marker.report_error(error);
+            }
+            (dom, content)
+        });
+
         struct State<N: GenericNode> {
             element: N,
             marker: N,
             children: Vec<N>,
+            /// The owner of each of the `children`.
+            owners: Vec<Owner>,
         }
 
         impl<N: GenericNode> State<N> {
@@ -75,100 +90,407 @@ where
                     element,
                     marker,
                     children: vec![],
+                    owners: vec![],
                 }))
             }
 
-            fn clear(&mut self) {
+            fn clear(&mut self) -> Result<(), Error> {
+                for owner in self.owners.drain(..) {
+                    owner.dispose();
+                }
                 for dom in self.children.drain(..) {
-                    self.element.remove_child(&dom);
+                    self.element.remove_child(&dom)?;
                     drop(dom)
                 }
+                Ok(())
             }
 
-            fn insert_at(&self, new_index: usize, child: &N) {
+            fn insert_at(&self, new_index: usize, child: &N) -> Result<(), Error> {
                 if let Some(dom) = self.children.get(new_index) {
-                    self.element.insert_child_before(child, Some(dom));
+                    self.element.insert_child_before(child, Some(dom))
                 } else {
-                    self.element.insert_child_before(child, Some(&self.marker));
+                    self.element.insert_child_before(child, Some(&self.marker))
                 }
             }
 
             // TODO verify that this will drop `children`
-            fn process_change(&mut self, change: VecDiff<N>) {
+            fn process_change(&mut self, change: VecDiff<(N, Owner)>) -> Result<(), Error> {
                 match change {
                     VecDiff::Replace { values } => {
-                        self.clear();
-                        self.children = values;
+                        self.clear()?;
+                        (self.children, self.owners) = values.into_iter().unzip();
                         for dom in self.children.iter_mut() {
-                            self.element.insert_child_before(dom, Some(&self.marker));
+                            self.element.insert_child_before(dom, Some(&self.marker))?;
                         }
+                        self.owners.iter().for_each(Owner::mount);
                     }
 
-                    VecDiff::InsertAt { index, value } => {
-                        self.insert_at(index, &value);
+                    VecDiff::InsertAt {
+                        index,
+                        value: (value, owner),
+                    } => {
+                        self.owners.insert(index, owner.clone());
+                        self.insert_at(index, &value)?;
                         self.children.insert(index, value);
+                        owner.mount();
                     }
 
-                    VecDiff::Push { value } => {
+                    VecDiff::Push {
+                        value: (value, owner),
+                    } => {
+                        self.owners.push(owner.clone());
                         let marker = self.marker.clone();
-                        self.element.insert_child_before(&value, Some(&marker));
+                        self.element.insert_child_before(&value, Some(&marker))?;
                         self.children.push(value);
+                        owner.mount();
                     }
 
-                    VecDiff::UpdateAt { index, mut value } => {
+                    VecDiff::UpdateAt {
+                        index,
+                        value: (mut value, owner),
+                    } => {
+                        std::mem::replace(&mut self.owners[index], owner.clone()).dispose();
                         let dom = &mut self.children[index];
-                        self.element.replace_child(&value, &self.marker);
+                        self.element.replace_child(dom, &value)?;
                         ::std::mem::swap(dom, &mut value);
+                        owner.mount();
                     }
 
                     VecDiff::Move {
                         old_index,
                         new_index,
                     } => {
+                        let owner = self.owners.remove(old_index);
+                        self.owners.insert(new_index, owner);
                         let value = self.children.remove(old_index);
 
-                        self.insert_at(new_index, &value);
+                        self.insert_at(new_index, &value)?;
 
                         self.children.insert(new_index, value);
                     }
 
                     VecDiff::RemoveAt { index } => {
+                        self.owners.remove(index).dispose();
                         let dom = self.children.remove(index);
-                        let children = dom.children().take();
-                        for child in children {
-                            child.remove_self()
-                        }
-
+                        self.element.remove_child(&dom)?;
                         drop(dom)
                     }
 
                     VecDiff::Pop {} => {
+                        if let Some(owner) = self.owners.pop() {
+                            owner.dispose();
+                        }
                         // TODO: change to unwrap_throw
                         let dom = self.children.pop().unwrap();
-                        let children = dom.children().take();
-                        for child in children {
-                            child.remove_self()
-                        }
+                        self.element.remove_child(&dom)?;
                         drop(dom)
                     }
 
                     VecDiff::Clear {} => {
-                        self.clear();
+                        self.clear()?;
                     }
                 }
+                Ok(())
             }
         }
 
-        parent.append_child(&marker.clone());
+        parent.append_child(&marker.clone())?;
 
         let state = State::new(parent.clone(), marker);
 
         let fut = iterable.for_each(move |change| {
             let mut state = state.borrow_mut();
-            state.process_change(change);
+            if let Err(error) = state.process_change(change) {
+                state.marker.report_error(error);
+            }
             ready(())
         });
         parent.effect(fut);
         Ok(())
     }
 }
+
+/// Props for [`Keyed`].
+#[derive(Debug)]
+pub struct KeyedProps<T, I: SignalVec<Item = T> + Unpin, F, K, Key, G: GenericNode>
+where
+    F: Fn(T) -> G,
+    K: Fn(&T) -> Key,
+    Key: Clone + Hash + Eq,
+{
+    pub iterable: I,
+    pub template: F,
+    pub key: K,
+}
+
+/// Keyed iteration. Each item is identified by the value returned from `key`, and the node
+/// rendered for that key is kept for as long as the key is present in the list.
+///
+/// Moving an item moves its existing node (together with its effects, event handlers, focus and
+/// scroll state) instead of rendering it again. Nodes are only created for keys that were added
+/// and only removed for keys that are gone. An item that is updated in place but keeps its key
+/// also keeps its node, so any data that can change should be reactive inside the template.
+///
+/// Keys must be unique within the list and `template` should return a single root node.
+///
+/// For non keyed iteration, see [`Indexed`].
+///
+/// # Example
+/// ```rust,no_run
+/// use hirola::prelude::*;
+/// use hirola::dom::Dom;
+/// let rows = MutableVec::new_with_values(vec![(1, "one"), (2, "two")]);
+///
+/// let res = html! {
+///  <ul>
+///     <Keyed
+///         props={KeyedProps {
+///             iterable: rows.signal_vec_cloned(),
+///             template: |(_, label)| html! { <li>{label}</li> },
+///             key: |(id, _)| *id,
+///         }}
+///     />
+///  </ul>
+/// };
+/// # let _ : Dom = res;
+/// ```
+pub struct Keyed<T, I: SignalVec<Item = T> + Unpin, F, K, Key, G: GenericNode>
+where
+    F: Fn(T) -> G,
+    K: Fn(&T) -> Key,
+    Key: Clone + Hash + Eq,
+{
+    pub props: KeyedProps<T, I, F, K, Key, G>,
+}
+
+impl<T, I, F, K, Key, N> Render<N> for Keyed<T, I, F, K, Key, N>
+where
+    T: 'static,
+    I: 'static + SignalVec<Item = T> + Unpin,
+    F: Fn(T) -> N + 'static,
+    K: Fn(&T) -> Key + 'static,
+    Key: Clone + Hash + Eq + 'static,
+    N: GenericNode,
+{
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        let KeyedProps {
+            iterable,
+            template,
+            key,
+        } = self.props;
+        let marker = N::marker();
+
+        struct State<T, F, K, Key, N: GenericNode> {
+            element: N,
+            marker: N,
+            template: F,
+            key: K,
+            children: Vec<(Key, N)>,
+            /// The owner of the content rendered for each key.
+            owners: HashMap<Key, Owner>,
+            owner: Option<Owner>,
+            _item: PhantomData<T>,
+        }
+
+        impl<T, F, K, Key, N> State<T, F, K, Key, N>
+        where
+            F: Fn(T) -> N,
+            K: Fn(&T) -> Key,
+            Key: Clone + Hash + Eq,
+            N: GenericNode,
+        {
+            fn render(&mut self, value: T) -> (Key, N) {
+                let key = (self.key)(&value);
+                let owner = Owner::new(self.owner.as_ref());
+                let dom = owner.run(|| (self.template)(value));
+                if let Some(error) = dom.take_error() {
+                    self.marker.report_error(error);
+                }
+                if let Some(previous) = self.owners.insert(key.clone(), owner) {
+                    previous.dispose();
+                }
+                (key, dom)
+            }
+
+            fn mount(&self, key: &Key) {
+                if let Some(owner) = self.owners.get(key) {
+                    owner.mount();
+                }
+            }
+
+            fn dispose(&mut self, key: &Key) {
+                if let Some(owner) = self.owners.remove(key) {
+                    owner.dispose();
+                }
+            }
+
+            /// The node that the item at `index` should be inserted before.
+            fn reference(&self, index: usize) -> &N {
+                self.children
+                    .get(index)
+                    .map(|(_, dom)| dom)
+                    .unwrap_or(&self.marker)
+            }
+
+            fn clear(&mut self) -> Result<(), Error> {
+                for (_, owner) in self.owners.drain() {
+                    owner.dispose();
+                }
+                for (_, dom) in self.children.drain(..) {
+                    self.element.remove_child(&dom)?;
+                }
+                Ok(())
+            }
+
+            /// Replaces all the items, reusing the nodes of every key that is still present
+            /// and moving only the nodes that are out of order.
+            fn reconcile(&mut self, values: Vec<T>) -> Result<(), Error> {
+                let mut previous: HashMap<Key, (usize, N)> = self
+                    .children
+                    .drain(..)
+                    .enumerate()
+                    .map(|(index, (key, dom))| (key, (index, dom)))
+                    .collect();
+                let mut sources = Vec::with_capacity(values.len());
+                let mut children = Vec::with_capacity(values.len());
+                for value in values {
+                    let key = (self.key)(&value);
+                    match previous.remove(&key) {
+                        Some((index, dom)) => {
+                            sources.push(Some(index));
+                            children.push((key, dom));
+                        }
+                        None => {
+                            sources.push(None);
+                            children.push(self.render(value));
+                        }
+                    }
+                }
+                self.children = children;
+                for (key, (_, dom)) in previous {
+                    self.dispose(&key);
+                    self.element.remove_child(&dom)?;
+                }
+                let stable = longest_increasing_subsequence(&sources);
+                let mut stable = stable.into_iter().rev().peekable();
+                let mut next = self.marker.clone();
+                for (index, (_, dom)) in self.children.iter().enumerate().rev() {
+                    if stable.peek() == Some(&index) {
+                        stable.next();
+                    } else {
+                        self.element.insert_child_before(dom, Some(&next))?;
+                    }
+                    next = dom.clone();
+                }
+                for (key, _) in &self.children {
+                    self.mount(key);
+                }
+                Ok(())
+            }
+
+            fn process_change(&mut self, change: VecDiff<T>) -> Result<(), Error> {
+                match change {
+                    VecDiff::Replace { values } => self.reconcile(values)?,
+                    VecDiff::InsertAt { index, value } => {
+                        let child = self.render(value);
+                        self.element
+                            .insert_child_before(&child.1, Some(self.reference(index)))?;
+                        self.mount(&child.0);
+                        self.children.insert(index, child);
+                    }
+                    VecDiff::Push { value } => {
+                        let child = self.render(value);
+                        self.element
+                            .insert_child_before(&child.1, Some(&self.marker))?;
+                        self.mount(&child.0);
+                        self.children.push(child);
+                    }
+                    VecDiff::UpdateAt { index, value } => {
+                        if (self.key)(&value) != self.children[index].0 {
+                            let child = self.render(value);
+                            let (key, old) = std::mem::replace(&mut self.children[index], child);
+                            self.dispose(&key);
+                            self.element
+                                .insert_child_before(&self.children[index].1, Some(&old))?;
+                            self.element.remove_child(&old)?;
+                            self.mount(&self.children[index].0);
+                        }
+                    }
+                    VecDiff::Move {
+                        old_index,
+                        new_index,
+                    } => {
+                        let child = self.children.remove(old_index);
+                        self.element
+                            .insert_child_before(&child.1, Some(self.reference(new_index)))?;
+                        self.children.insert(new_index, child);
+                    }
+                    VecDiff::RemoveAt { index } => {
+                        let (key, dom) = self.children.remove(index);
+                        self.dispose(&key);
+                        self.element.remove_child(&dom)?;
+                    }
+                    VecDiff::Pop {} => {
+                        if let Some((key, dom)) = self.children.pop() {
+                            self.dispose(&key);
+                            self.element.remove_child(&dom)?;
+                        }
+                    }
+                    VecDiff::Clear {} => self.clear()?,
+                }
+                Ok(())
+            }
+        }
+
+        parent.append_child(&marker)?;
+        let state = RefCell::new(State {
+            element: parent.clone(),
+            marker,
+            template,
+            key,
+            children: vec![],
+            owners: HashMap::new(),
+            owner: Owner::current(),
+            _item: PhantomData,
+        });
+        let fut = iterable.for_each(move |change| {
+            let mut state = state.borrow_mut();
+            if let Err(error) = state.process_change(change) {
+                state.marker.report_error(error);
+            }
+            ready(())
+        });
+        parent.effect(fut);
+        Ok(())
+    }
+}
+
+/// Returns the positions in `sources` that form the longest run of reused nodes which are
+/// already in increasing order. Those nodes can stay where they are while the rest are moved.
+fn longest_increasing_subsequence(sources: &[Option<usize>]) -> Vec<usize> {
+    // `tails[len]` is the position ending the smallest tail of a run of length `len + 1`
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors = vec![None; sources.len()];
+    for (position, source) in sources.iter().enumerate() {
+        let Some(source) = source else {
+            continue;
+        };
+        let len = tails.partition_point(|&tail| sources[tail] < Some(*source));
+        if len > 0 {
+            predecessors[position] = Some(tails[len - 1]);
+        }
+        if len == tails.len() {
+            tails.push(position);
+        } else {
+            tails[len] = position;
+        }
+    }
+    let mut run = Vec::with_capacity(tails.len());
+    let mut current = tails.last().copied();
+    while let Some(position) = current {
+        run.push(position);
+        current = predecessors[position];
+    }
+    run.reverse();
+    run
+}
@@ -1,3 +1,6 @@
+pub mod error_boundary;
 pub mod flow;
+pub mod portal;
+pub mod styled;
 pub mod suspense;
 pub mod switch;
@@ -0,0 +1,149 @@
+//! Rendering content into a node other than the one it is declared in, eg. modals and toasts that
+//! need to escape an `overflow: hidden` container.
+use crate::{
+    generic_node::GenericNode,
+    owner::Owner,
+    render::{Error, Render},
+};
+use std::{any::Any, cell::RefCell, collections::HashMap};
+
+thread_local! {
+    static ROOTS: RefCell<HashMap<String, Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// Registers `node` as the portal root `name`, replacing the root registered under that name.
+pub fn register_portal_root<N: GenericNode>(name: impl Into<String>, node: &N) {
+    ROOTS.with(|roots| {
+        roots
+            .borrow_mut()
+            .insert(name.into(), Box::new(node.clone()))
+    });
+}
+
+/// Removes the portal root `name`. Portals already rendered into it are left in place.
+pub fn unregister_portal_root(name: &str) {
+    ROOTS.with(|roots| roots.borrow_mut().remove(name));
+}
+
+/// The node registered as the portal root `name`, if it is a node of type `N`.
+pub fn portal_root<N: GenericNode>(name: &str) -> Option<N> {
+    ROOTS.with(|roots| {
+        roots
+            .borrow()
+            .get(name)
+            .and_then(|root| root.downcast_ref::<N>())
+            .cloned()
+    })
+}
+
+/// Registers the node it is rendered into as the portal root `name`, until the content it was
+/// rendered in is removed.
+///
+/// ```rust,ignore
+/// html! { <body><main>{page}</main><div>{PortalRoot { name: "modals" }}</div></body> }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PortalRoot {
+    pub name: &'static str,
+}
+
+impl<N: GenericNode> Render<N> for PortalRoot {
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        register_portal_root(self.name, parent);
+        if let Some(owner) = Owner::current() {
+            let parent = parent.clone();
+            owner.on_cleanup(move || {
+                if portal_root::<N>(self.name).as_ref() == Some(&parent) {
+                    unregister_portal_root(self.name);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Where a [`Portal`] renders its children.
+#[derive(Debug, Clone)]
+pub enum PortalTarget<N> {
+    Node(N),
+    /// A root registered with [`PortalRoot`] or [`register_portal_root`].
+    Root(String),
+}
+
+/// Renders `children` at the end of `target` instead of where the portal is declared.
+///
+/// The children still belong to the place the portal is declared in: errors raised by their
+/// effects and event handlers go to the enclosing
+/// [`ErrorBoundary`](crate::templating::error_boundary::ErrorBoundary), and they are removed from
+/// `target` once the content the portal was rendered in is removed, eg. by a
+/// [`Match`](crate::templating::switch::Match) switching to another view.
+///
+/// ```rust,ignore
+/// html! {
+///     <div class="card">
+///         {Portal::to_root("modals", html! { <dialog open="">"Saved"</dialog> })}
+///     </div>
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Portal<N> {
+    pub target: PortalTarget<N>,
+    pub children: N,
+}
+
+impl<N: GenericNode> Portal<N> {
+    /// Renders `children` into `target`.
+    pub fn new(target: N, children: N) -> Self {
+        Portal {
+            target: PortalTarget::Node(target),
+            children,
+        }
+    }
+
+    /// Renders `children` into the portal root `name`.
+    pub fn to_root(name: impl Into<String>, children: N) -> Self {
+        Portal {
+            target: PortalTarget::Root(name.into()),
+            children,
+        }
+    }
+}
+
+/// Removes `start`, `end` and the nodes between them.
+fn remove_range<N: GenericNode>(start: &N, end: &N) -> Result<(), Error> {
+    let Some(holder) = end.parent_node() else {
+        return Ok(());
+    };
+    while let Some(node) = start.next_sibling() {
+        holder.remove_child(&node)?;
+        if &node == end {
+            break;
+        }
+    }
+    holder.remove_child(start)
+}
+
+impl<N: GenericNode> Render<N> for Portal<N> {
+    fn render_into(self: Box<Self>, _parent: &N) -> Result<(), Error> {
+        let target = match self.target {
+            PortalTarget::Node(target) => target,
+            PortalTarget::Root(name) => {
+                portal_root::<N>(&name).ok_or(Error::MissingPortalRoot(name))?
+            }
+        };
+        if let Some(error) = self.children.take_error() {
+            return Err(error);
+        }
+        let start = N::marker();
+        let end = N::marker();
+        target.append_child(&start)?;
+        target.append_child(&self.children)?;
+        target.append_child(&end)?;
+        if let Some(owner) = Owner::current() {
+            owner.on_cleanup(move || {
+                remove_range(&start, &end).unwrap_or_else(|error| end.report_error(error))
+            });
+        }
+        Ok(())
+    }
+}
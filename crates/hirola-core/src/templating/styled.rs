@@ -1,15 +1,165 @@
-use regex::Regex;
+//! Styles scoped to a component, eg. a [`Style`] built with [`style!`](crate::style).
+//!
+//! The class selectors of a component's style are prefixed with a hash of its type, and
+//! [`Styled::class`] returns the prefixed class names to use in its markup. The rules of every
+//! component are passed once to the [`StyleBackend`] of the renderer, eg. `hirola-dom` inserts
+//! them into a stylesheet. Until a backend is set, they are kept by a [`StyleCollector`].
+//!
+//! ```rust,ignore
+//! struct Card;
+//!
+//! impl Styled for Card {
+//!     fn style() -> Style {
+//!         style! {
+//!             ".card" { "padding": "1rem"; }
+//!         }
+//!     }
+//! }
+//!
+//! fn card() -> Dom {
+//!     Card::styled(html! { <div class=Card::class("card")>"Saved"</div> })
+//! }
+//! ```
 use std::any;
 use std::cell::RefCell;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
+use std::future::{self, Future};
 use std::hash::Hasher;
-use wasm_bindgen::{prelude::*, JsCast};
+use std::pin::pin;
+
+/// Receives the rules of the [`Styled`] components that are rendered.
+pub trait StyleBackend {
+    /// Whether the rules of the component `id` were inserted already.
+    fn contains(&self, id: u64) -> bool;
+
+    /// Inserts the rules of the component `id`, a hash of its type.
+    fn insert(&mut self, id: u64, rules: Vec<String>);
+}
+
+/// A [`StyleBackend`] that keeps the rules, in the order the components were first rendered in.
+#[derive(Debug, Default, Clone)]
+pub struct StyleCollector {
+    ids: HashSet<u64>,
+    components: Vec<(u64, Vec<String>)>,
+}
+
+impl StyleCollector {
+    /// The collected rules.
+    pub fn rules(&self) -> impl Iterator<Item = &str> {
+        self.components
+            .iter()
+            .flat_map(|(_, rules)| rules.iter().map(String::as_str))
+    }
+
+    /// The collected rules, as a stylesheet.
+    pub fn to_css(&self) -> String {
+        self.rules().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+impl StyleBackend for StyleCollector {
+    fn contains(&self, id: u64) -> bool {
+        self.ids.contains(&id)
+    }
+
+    fn insert(&mut self, id: u64, rules: Vec<String>) {
+        if self.ids.insert(id) {
+            self.components.push((id, rules));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    backend: Option<Box<dyn StyleBackend>>,
+    /// The rules inserted while there was no backend.
+    collected: StyleCollector,
+    /// The rules inserted while running [`collect_styles`], which go there instead.
+    scoped: Option<StyleCollector>,
+}
+
+impl Registry {
+    fn backend(&mut self) -> &mut dyn StyleBackend {
+        match (&mut self.scoped, &mut self.backend) {
+            (Some(scoped), _) => scoped,
+            (None, Some(backend)) => backend.as_mut(),
+            (None, None) => &mut self.collected,
+        }
+    }
+}
 
 thread_local! {
-    static STYLED: RefCell<HashSet<u64>> = RefCell::new(HashSet::new());
-    static SHEET: RefCell<Option<web_sys::CssStyleSheet>> = RefCell::new(None);
-    static CLASS_SELECTOR: Regex = Regex::new(r"\.([a-zA-Z][a-zA-Z0-9\-_]*)").unwrap();
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Sends the rules of the components rendered from now on to `backend`, along with the ones
+/// collected while there was no backend.
+pub fn set_style_backend(mut backend: impl StyleBackend + 'static) {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        for (id, rules) in std::mem::take(&mut registry.collected).components {
+            backend.insert(id, rules);
+        }
+        registry.backend = Some(Box::new(backend));
+    });
+}
+
+/// Whether a [`StyleBackend`] was set with [`set_style_backend`].
+pub fn has_style_backend() -> bool {
+    REGISTRY.with(|registry| registry.borrow().backend.is_some())
+}
+
+/// Takes the rules collected while there was no [`StyleBackend`], eg. to render them on the server.
+pub fn take_collected_styles() -> StyleCollector {
+    REGISTRY.with(|registry| std::mem::take(&mut registry.borrow_mut().collected))
+}
+
+/// Puts back the collector of the enclosing [`collect_styles`], if any, once dropped.
+struct Scope(Option<StyleCollector>);
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        REGISTRY.with(|registry| registry.borrow_mut().scoped = self.0.take());
+    }
+}
+
+/// Runs `f`, and returns the rules of the components built meanwhile, eg. the stylesheet of a
+/// single page rendered on the server.
+///
+/// The rules go to a new [`StyleCollector`] instead of the [`StyleBackend`], including the ones of
+/// the components that were inserted before.
+pub fn collect_styles<R>(f: impl FnOnce() -> R) -> (R, StyleCollector) {
+    collect_styles_into(StyleCollector::default(), f)
+}
+
+/// Like [`collect_styles`], for the components built while `future` is polled, eg. by
+/// `render_to_string_async`. The futures polled in between keep their own styles, so pages can be
+/// rendered concurrently on the same thread.
+pub async fn collect_styles_async<F: Future>(future: F) -> (F::Output, StyleCollector) {
+    let mut future = pin!(future);
+    let mut collected = StyleCollector::default();
+    let output = future::poll_fn(|cx| {
+        let (poll, styles) =
+            collect_styles_into(std::mem::take(&mut collected), || future.as_mut().poll(cx));
+        collected = styles;
+        poll
+    })
+    .await;
+    (output, collected)
+}
+
+/// Runs `f` with the rules going to `collector`.
+fn collect_styles_into<R>(collector: StyleCollector, f: impl FnOnce() -> R) -> (R, StyleCollector) {
+    let outer = REGISTRY.with(|registry| registry.borrow_mut().scoped.replace(collector));
+    let _scope = Scope(outer);
+    let result = f();
+    let collected = REGISTRY.with(|registry| registry.borrow_mut().scoped.take());
+    (result, collected.unwrap_or_default())
 }
 
 fn hash_of_type<C>() -> u64 {
@@ -19,27 +169,37 @@ fn hash_of_type<C>() -> u64 {
 }
 
 fn styled_class_prefix<C>() -> String {
-    let mut hasher = DefaultHasher::new();
-    hasher.write(any::type_name::<C>().as_bytes());
-    format!("{:X}", hasher.finish())
+    format!("{:X}", hash_of_type::<C>())
 }
 
 fn styled_class<C>(class_name: &str) -> String {
     format!("_{}__{}", styled_class_prefix::<C>(), class_name)
 }
 
+/// Prefixes the class names in `selector`, eg. `.card > .title`, with `prefix`.
+fn prefix_classes(selector: &str, prefix: &str) -> String {
+    let mut prefixed = String::with_capacity(selector.len());
+    let mut chars = selector.chars().peekable();
+    while let Some(c) = chars.next() {
+        prefixed.push(c);
+        if c == '.' && chars.peek().is_some_and(char::is_ascii_alphabetic) {
+            prefixed.push_str(&format!("_{prefix}__"));
+        }
+    }
+    prefixed
+}
+
 pub trait Styled: Sized {
     fn style() -> Style;
+    /// Inserts the style of the component into the [`StyleBackend`] the first time it is
+    /// rendered, and returns `node`.
     fn styled<T>(node: T) -> T {
-        STYLED.with(|styled| {
-            let component_id = hash_of_type::<Self>();
-            if styled.borrow().get(&component_id).is_none() {
-                let style = Self::style();
-                style.write::<Self>();
-                styled.borrow_mut().insert(component_id);
-            }
-        });
-
+        let id = hash_of_type::<Self>();
+        let inserted = REGISTRY.with(|registry| registry.borrow_mut().backend().contains(id));
+        if !inserted {
+            let rules = Self::style().rules::<Self>();
+            REGISTRY.with(|registry| registry.borrow_mut().backend().insert(id, rules));
+        }
         node
     }
     fn class(class_name: &str) -> String {
@@ -54,7 +214,7 @@ enum Rule {
     Media(String, Style),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Style {
     rules: Vec<Rule>,
 }
@@ -128,13 +288,7 @@ impl Style {
             let str_rule = match rule {
                 Rule::Selector(selecter, defs) => {
                     let mut str_rule = String::new();
-                    let str_selecter = CLASS_SELECTOR.with(|class_selecter| {
-                        class_selecter.replace_all(
-                            selecter,
-                            format!("._{}__$1", styled_class_prefix::<C>()).as_str(),
-                        )
-                    });
-                    str_rule += &str_selecter;
+                    str_rule += &prefix_classes(selecter, &styled_class_prefix::<C>());
                     str_rule += "{";
                     for (property, value) in defs {
                         str_rule += format!("{}:{};", property, value).as_str();
@@ -173,64 +327,6 @@ impl Style {
 
         str_rules
     }
-
-    fn write<C>(&self) {
-        Self::add_style_element();
-
-        for rule in &self.rules::<C>() {
-            SHEET.with(|sheet| {
-                if let Some(sheet) = sheet.borrow().as_ref() {
-                    if let Err(err) = sheet
-                        .insert_rule_with_index(rule.as_str(), sheet.css_rules().unwrap().length())
-                    {
-                        web_sys::console::log_1(&JsValue::from(err));
-                    }
-                }
-            });
-        }
-    }
-
-    fn add_style_element() {
-        SHEET.with(|sheet| {
-            if sheet.borrow().is_none() {
-                let style_element = web_sys::window()
-                    .unwrap()
-                    .document()
-                    .unwrap()
-                    .create_element("style")
-                    .unwrap()
-                    .dyn_into::<web_sys::HtmlStyleElement>()
-                    .unwrap();
-
-                let head = web_sys::window()
-                    .unwrap()
-                    .document()
-                    .unwrap()
-                    .get_elements_by_tag_name("head")
-                    .item(0)
-                    .unwrap();
-
-                let _ = head.append_child(&style_element);
-
-                *sheet.borrow_mut() = Some(
-                    style_element
-                        .sheet()
-                        .unwrap()
-                        .dyn_into::<web_sys::CssStyleSheet>()
-                        .unwrap(),
-                );
-            }
-        });
-    }
-}
-
-macro_rules! return_if {
-    ($x:ident = $y:expr; $z: expr) => {{
-        let $x = $y;
-        if $z {
-            return $x;
-        }
-    }};
 }
 
 impl std::fmt::Display for Style {
@@ -238,40 +334,32 @@ impl std::fmt::Display for Style {
         for rule in &self.rules {
             match rule {
                 Rule::Selector(selecter, defs) => {
-                    return_if!(x = write!(f, "{} {}\n", selecter, "{"); x.is_err());
+                    writeln!(f, "{} {{", selecter)?;
                     for (property, value) in defs {
-                        return_if!(x = write!(f, "    {}: {};\n", property, value); x.is_err());
+                        writeln!(f, "    {}: {};", property, value)?;
                     }
-                    return_if!(x = write!(f, "{}\n", "}"); x.is_err());
+                    writeln!(f, "}}")?;
                 }
 
                 Rule::Keyframes(name, keyframes) => {
-                    return_if!(x = write!(f, "@keyframes {} {}\n", name, "{"); x.is_err());
-
-                    for a_line in format!("{}", keyframes).split("\n") {
-                        if a_line != "" {
-                            return_if!(x = write!(f, "    {}\n", a_line); x.is_err());
-                        }
+                    writeln!(f, "@keyframes {} {{", name)?;
+                    for a_line in keyframes.to_string().lines() {
+                        writeln!(f, "    {}", a_line)?;
                     }
-
-                    return_if!(x = write!(f, "{}\n", "}"); x.is_err());
+                    writeln!(f, "}}")?;
                 }
 
                 Rule::Media(query, style) => {
-                    return_if!(x = write!(f, "@media {} {}\n", query, "{"); x.is_err());
-
-                    for a_line in format!("{}", style).split("\n") {
-                        if a_line != "" {
-                            return_if!(x = write!(f, "    {}\n", a_line); x.is_err());
-                        }
+                    writeln!(f, "@media {} {{", query)?;
+                    for a_line in style.to_string().lines() {
+                        writeln!(f, "    {}", a_line)?;
                     }
-
-                    return_if!(x = write!(f, "{}\n", "}"); x.is_err());
+                    writeln!(f, "}}")?;
                 }
             }
         }
 
-        write!(f, "")
+        Ok(())
     }
 }
 
@@ -291,7 +379,7 @@ macro_rules! style {
     } => {{
         $inst.append(&($import));
 
-        style! {
+        $crate::style! {
             instance: $inst;
             $($others)*
         }
@@ -304,7 +392,7 @@ macro_rules! style {
     } => {{
         $inst.append(&($extends));
 
-        style! {
+        $crate::style! {
             instance: $inst;
             $($others)*
         }
@@ -315,9 +403,9 @@ macro_rules! style {
         @keyframes $name:tt {$($keyframes:tt)*}
         $($others:tt)*
     } => {{
-        $inst.add_keyframes($name, style!{$($keyframes)*});
+        $inst.add_keyframes($name, $crate::style!{$($keyframes)*});
 
-        style! {
+        $crate::style! {
             instance: $inst;
             $($others)*
         }
@@ -328,9 +416,9 @@ macro_rules! style {
         @media $query:tt {$($media_style:tt)*}
         $($others:tt)*
     } => {{
-        $inst.add_media($query, style!{$($media_style)*});
+        $inst.add_media($query, $crate::style!{$($media_style)*});
 
-        style! {
+        $crate::style! {
             instance: $inst;
             $($others)*
         }
@@ -347,7 +435,7 @@ macro_rules! style {
             $inst.add(format!("{}", $selector), format!("{}", $property), format!("{}", $value));
         )*
 
-        style! {
+        $crate::style! {
             instance: $inst;
             $($others)*
         }
@@ -361,9 +449,9 @@ macro_rules! style {
         $($others:tt)*
     } => {{
         #[allow(unused_mut)]
-        let mut instance = Style::new();
+        let mut instance = $crate::templating::styled::Style::new();
 
-        style! {
+        $crate::style! {
             instance: instance;
             $($others)*
         };
@@ -376,11 +464,7 @@ macro_rules! style {
 mod tests {
     use super::Rule;
     use super::Style;
-
-    #[test]
-    fn it_works() {
-        assert!(true);
-    }
+    use super::{styled_class_prefix, take_collected_styles, Styled};
 
     #[test]
     fn debug_style() {
@@ -629,4 +713,38 @@ mod tests {
 
         assert_eq!(style_a, style_b);
     }
+
+    struct Card;
+
+    impl Styled for Card {
+        fn style() -> Style {
+            style! {
+                ".card > .title" {
+                    "color": "red";
+                }
+
+                @media "(min-width: 640px)" {
+                    ".card" {
+                        "padding": "1rem";
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn styled_rules_are_prefixed_and_collected_once() {
+        Card::styled(());
+        Card::styled(());
+
+        let prefix = styled_class_prefix::<Card>();
+        assert_eq!(Card::class("card"), format!("_{prefix}__card"));
+        assert_eq!(
+            take_collected_styles().to_css(),
+            format!(
+                "._{prefix}__card > ._{prefix}__title{{color:red;}}\
+                 @media (min-width: 640px){{._{prefix}__card{{padding:1rem;}}}}"
+            )
+        );
+    }
 }
@@ -1,16 +1,18 @@
 use crate::{
     generic_node::GenericNode,
+    owner::Owner,
     render::{Error, Render},
     BoxedLocal,
 };
-use futures_util::future::FutureExt;
-use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
+use futures_util::future::{self, FutureExt, TryFutureExt};
+use std::{cell::RefCell, convert::Infallible, future::Future, pin::Pin, rc::Rc, task::Poll};
 
-#[derive(Debug, Default)]
-pub enum SuspenseResult<Res> {
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum SuspenseResult<Res, E = Infallible> {
     #[default]
     Loading,
     Ready(Res),
+    Failed(E),
 }
 
 pub trait Suspend {
@@ -28,62 +30,180 @@ where
     }
 }
 
+/// Like [`Suspend`], for futures that can fail.
+/// `Ok` resolves to [`SuspenseResult::Ready`] and `Err` to [`SuspenseResult::Failed`].
+pub trait TrySuspend {
+    type Ok;
+    type Error;
+    fn try_suspend(self) -> BoxedLocal<SuspenseResult<Self::Ok, Self::Error>>;
+}
+
+impl<F, T, E> TrySuspend for F
+where
+    F: Future<Output = Result<T, E>> + 'static,
+    T: 'static,
+    E: 'static,
+{
+    type Ok = T;
+    type Error = E;
+    fn try_suspend(self) -> BoxedLocal<SuspenseResult<T, E>> {
+        Box::pin(self.map_ok_or_else(SuspenseResult::Failed, SuspenseResult::Ready))
+    }
+}
+
+/// Renders `template` with the output of `future` once it resolves.
+///
+/// Until then, the [fallback](Suspense::with_fallback) is rendered if there is one. If the
+/// [timeout](Suspense::with_timeout) resolves first, its output is rendered instead and `future`
+/// is dropped.
+///
+/// The future is dropped without rendering anything once the content the boundary was rendered
+/// in is disposed, eg. when a `Match` switches away from it.
+///
+/// ```rust,ignore
+/// Suspense::new(fetch_page(), |page: Page| html! { <p>{page.title}</p> })
+///     .with_fallback(|| html! { <p>"Loading"</p> })
+///     .with_timeout(sleep(Duration::from_secs(2)).map(|_| Page::timed_out()))
+/// ```
 pub struct Suspense<Res, G> {
-    pub template: Box<dyn Fn(Res) -> G>,
-    pub future: Pin<Box<dyn Future<Output = Res>>>,
+    template: Rc<dyn Fn(Res) -> G>,
+    future: Pin<Box<dyn Future<Output = Res>>>,
+    fallback: Option<Box<dyn Fn() -> G>>,
+    timeout: Option<Pin<Box<dyn Future<Output = Res>>>>,
+}
+
+impl<Res: 'static, G: 'static> Suspense<Res, G> {
+    /// Creates a boundary that renders nothing while waiting.
+    pub fn new(
+        future: impl Future<Output = Res> + 'static,
+        template: impl Fn(Res) -> G + 'static,
+    ) -> Self {
+        Suspense {
+            template: Rc::new(template),
+            future: Box::pin(future),
+            fallback: None,
+            timeout: None,
+        }
+    }
+
+    /// Renders `fallback` while waiting.
+    pub fn with_fallback(mut self, fallback: impl Fn() -> G + 'static) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Renders `template` with the output of `timeout` if it resolves before the future, which
+    /// is then dropped.
+    pub fn with_timeout(mut self, timeout: impl Future<Output = Res> + 'static) -> Self {
+        self.timeout = Some(Box::pin(timeout));
+        self
+    }
+}
+
+impl<Res: Default + 'static, G: 'static> Suspense<Res, G> {
+    /// Renders `template(Res::default())` while waiting, eg. [`SuspenseResult::Loading`]. This is
+    /// what `{match future.await { .. }}` in `html!` uses.
+    pub fn with_default_fallback(self) -> Self {
+        let template = Rc::clone(&self.template);
+        self.with_fallback(move || template(Res::default()))
+    }
+}
+
+impl<T: 'static, E: 'static, G: 'static> Suspense<SuspenseResult<T, E>, G> {
+    /// Creates a boundary for a future that can fail. `template` renders
+    /// [`SuspenseResult::Loading`] while waiting, then [`SuspenseResult::Ready`] or
+    /// [`SuspenseResult::Failed`].
+    pub fn try_new(
+        future: impl Future<Output = Result<T, E>> + 'static,
+        template: impl Fn(SuspenseResult<T, E>) -> G + 'static,
+    ) -> Self {
+        Suspense::new(future.try_suspend(), template).with_default_fallback()
+    }
 }
 
-impl<Res: Default + 'static, N: GenericNode> Render<N> for Suspense<Res, N> {
+impl<Res: 'static, N: GenericNode> Render<N> for Suspense<Res, N> {
     fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
-        let template = self.template;
+        let Suspense {
+            template,
+            future,
+            fallback,
+            timeout,
+        } = *self;
         struct State<N> {
             holder: N,
-            current: Option<N>,
+            marker: N,
+            current: Option<(N, Owner)>,
         }
 
         impl<N: GenericNode> State<N> {
-            fn new(parent: N) -> Rc<RefCell<Self>> {
+            fn new(parent: N, marker: N) -> Rc<RefCell<Self>> {
                 Rc::new(RefCell::new(State {
                     holder: parent,
+                    marker,
                     current: None,
                 }))
             }
 
-            fn clear(&mut self) {
-                {
-                    let node = &mut self.holder;
-                    if let Some(frag) = &self.current {
-                        for child in &frag.children().take() {
-                            node.remove_child(child);
-                        }
-                    };
+            fn clear(&mut self) -> Result<(), Error> {
+                if let Some((frag, owner)) = self.current.take() {
+                    owner.dispose();
+                    for child in &frag.children().take() {
+                        self.holder.remove_child(child)?;
+                    }
                 }
-                self.current = None;
+                Ok(())
             }
 
-            fn apply(&mut self, dom: N) -> Result<(), Error> {
-                self.clear();
-                let node = &mut self.holder;
+            fn apply(&mut self, dom: N, owner: Owner) -> Result<(), Error> {
                 let frag = N::fragment();
-                frag.append_child(&dom);
-                node.append_child(&frag);
-                self.current = Some(frag);
+                if let Err(error) = frag.append_built(&dom) {
+                    owner.dispose();
+                    return Err(error);
+                }
+                self.clear()?;
+                self.holder.insert_child_before(&frag, Some(&self.marker))?;
+                owner.mount();
+                self.current = Some((frag, owner));
                 Ok(())
             }
         }
 
-        let state = State::new(parent.clone());
-        let binding = Rc::<_>::clone(&state);
-        let mut binding = binding.borrow_mut();
-        // Apply loading
-        binding.apply(template(Res::default()))?;
-        let future = self.future;
+        let marker = N::marker();
+        parent.append_child(&marker)?;
+        let state = State::new(parent.clone(), marker.clone());
+        let owner = Owner::current();
+        // The content always sits in a fragment right before the marker, even when it is empty,
+        // which is where streaming SSR finds the boundary
+        let content = Owner::new(owner.as_ref());
+        let dom = match fallback {
+            Some(fallback) => content.run(fallback),
+            None => N::fragment(),
+        };
+        state.borrow_mut().apply(dom, content)?;
+        let mut future = match timeout {
+            Some(timeout) => future::select(future, timeout)
+                .map(|either| either.factor_first().0)
+                .boxed_local(),
+            None => future,
+        };
+        let parent_owner = owner.clone();
+        let mounted = future::poll_fn(move |cx| {
+            if parent_owner.as_ref().is_some_and(Owner::is_disposed) {
+                return Poll::Ready(None);
+            }
+            future.as_mut().poll(cx).map(Some)
+        });
         let fut = async move {
-            let new_dom = template(future.await);
-            let mut state = state.borrow_mut();
-            state.apply(new_dom).unwrap();
+            if let Some(res) = mounted.await {
+                let content = Owner::new(owner.as_ref());
+                let new_dom = content.run(|| template(res));
+                let mut state = state.borrow_mut();
+                if let Err(error) = state.apply(new_dom, content) {
+                    state.marker.report_error(error);
+                }
+            }
         };
-        parent.effect(fut);
+        marker.suspense(fut);
         Ok(())
     }
 }
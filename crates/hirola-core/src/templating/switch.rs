@@ -4,6 +4,7 @@ use futures_signals::signal::{Signal, SignalExt};
 
 use crate::{
     generic_node::GenericNode,
+    owner::Owner,
     render::{Error, Render},
 };
 
@@ -21,51 +22,171 @@ where
     S: Signal<Item = bool> + 'static,
 {
     fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
-        let marker = N::marker();
-        parent.append_child(&marker);
-        let state = State::new(parent.clone(), marker);
-        let renderer = self.renderer;
-        struct State<DomType: GenericNode> {
-            holder: DomType,
-            marker: DomType,
-            current: Option<DomType>,
+        Box::new(Match::new(self.signal, |value| *value, self.renderer)).render_into(parent)
+    }
+}
+
+/// Renders the value of `signal` with `renderer`, switching between views as it changes.
+///
+/// The view is only rebuilt when the key returned by `key` changes, eg. the variant of an enum or
+/// the arm of a `match`. Changes that keep the same key leave the current view in place.
+pub struct Match<S, K, F> {
+    pub signal: S,
+    pub key: K,
+    pub renderer: F,
+}
+
+impl<S, K, F> Match<S, K, F> {
+    pub fn new<T, Key, G>(signal: S, key: K, renderer: F) -> Self
+    where
+        S: Signal<Item = T>,
+        K: Fn(&T) -> Key,
+        Key: PartialEq,
+        F: Fn(T) -> G,
+    {
+        Match {
+            signal,
+            key,
+            renderer,
         }
+    }
+}
 
-        impl<DomType: GenericNode> State<DomType> {
-            fn new(element: DomType, marker: DomType) -> Rc<RefCell<Self>> {
-                Rc::new(RefCell::new(State {
-                    holder: element,
-                    current: None,
-                    marker,
-                }))
+impl<S, K, F, Key, N: GenericNode> Render<N> for Match<S, K, F>
+where
+    S: Signal + 'static,
+    K: Fn(&S::Item) -> Key + 'static,
+    Key: PartialEq + 'static,
+    F: Fn(S::Item) -> N + 'static,
+{
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        let Match {
+            signal,
+            key,
+            renderer,
+        } = *self;
+        render_switching(parent, signal, move |value, content, current| {
+            let key = key(&value);
+            match current {
+                Some(current) if current == &key => None,
+                _ => Some((key, content.run(|| renderer(value)))),
             }
+        })
+    }
+}
 
-            fn clear(&mut self) {
-                {
-                    let node = &mut self.holder;
-                    if let Some(frag) = &self.current {
-                        for child in &frag.children().take() {
-                            node.remove_child(child)
-                        }
-                    };
-                }
-                self.current = None;
+/// Like [`Match`], with the key and the view returned together by `view`, eg. the index of the
+/// arm of a `match` and the view built by that arm.
+///
+/// The view is built for every value, and dropped when its key is the one of the current view.
+/// `html!` lowers `{match signal as Signal { .. }}` to a `MatchArms`, so that every arm, and its
+/// guard, is only evaluated once per value.
+pub struct MatchArms<S, F> {
+    signal: S,
+    view: F,
+}
+
+impl<S, F> MatchArms<S, F> {
+    pub fn new<T, Key, G>(signal: S, view: F) -> Self
+    where
+        S: Signal<Item = T>,
+        F: Fn(T) -> (Key, G),
+        Key: PartialEq,
+    {
+        MatchArms { signal, view }
+    }
+}
+
+impl<S, F, Key, N: GenericNode> Render<N> for MatchArms<S, F>
+where
+    S: Signal + 'static,
+    F: Fn(S::Item) -> (Key, N) + 'static,
+    Key: PartialEq + 'static,
+{
+    fn render_into(self: Box<Self>, parent: &N) -> Result<(), Error> {
+        let MatchArms { signal, view } = *self;
+        render_switching(parent, signal, move |value, content, current| {
+            let (key, dom) = content.run(|| view(value));
+            match current {
+                Some(current) if current == &key => None,
+                _ => Some((key, dom)),
             }
+        })
+    }
+}
+
+/// Renders the views returned by `view` for the values of `signal` before a marker, replacing
+/// the current one whenever `view` returns a new one.
+///
+/// `view` gets the owner of the new view and the key of the current one, and returns `None` to
+/// keep the current view.
+fn render_switching<N, S, Key>(
+    parent: &N,
+    signal: S,
+    view: impl Fn(S::Item, &Owner, Option<&Key>) -> Option<(Key, N)> + 'static,
+) -> Result<(), Error>
+where
+    N: GenericNode,
+    S: Signal + 'static,
+    Key: 'static,
+{
+    let marker = N::marker();
+    parent.append_child(&marker)?;
+    let state = State::new(parent.clone(), marker);
+    let owner = Owner::current();
+    struct State<DomType: GenericNode, Key> {
+        holder: DomType,
+        marker: DomType,
+        current: Option<(Key, DomType, Owner)>,
+    }
 
-            fn apply(&mut self, dom: DomType) {
-                self.clear();
-                let node = &self.holder;
-                node.insert_child_before(&dom, Some(&self.marker));
-                self.current = Some(dom);
+    impl<DomType: GenericNode, Key> State<DomType, Key> {
+        fn new(element: DomType, marker: DomType) -> Rc<RefCell<Self>> {
+            Rc::new(RefCell::new(State {
+                holder: element,
+                current: None,
+                marker,
+            }))
+        }
+
+        fn clear(&mut self) -> Result<(), Error> {
+            if let Some((_, frag, owner)) = self.current.take() {
+                owner.dispose();
+                for child in &frag.children().take() {
+                    self.holder.remove_child(child)?;
+                }
             }
+            Ok(())
         }
-        let fut = self.signal.for_each(move |val| {
-            let mut state = state.borrow_mut();
-            state.apply(renderer(val));
 
-            async {}
-        });
-        parent.effect(fut);
-        Ok(())
+        fn apply(&mut self, key: Key, dom: DomType, owner: Owner) -> Result<(), Error> {
+            let frag = DomType::fragment();
+            if let Err(error) = frag.append_built(&dom) {
+                owner.dispose();
+                return Err(error);
+            }
+            self.clear()?;
+            self.holder.insert_child_before(&frag, Some(&self.marker))?;
+            owner.mount();
+            self.current = Some((key, frag, owner));
+            Ok(())
+        }
     }
+    let fut = signal.for_each(move |val| {
+        let mut state = state.borrow_mut();
+        let content = Owner::new(owner.as_ref());
+        let current = state.current.as_ref().map(|(key, _, _)| key);
+        match view(val, &content, current) {
+            Some((key, dom)) => {
+                if let Err(error) = state.apply(key, dom, content) {
+                    state.marker.report_error(error);
+                }
+            }
+            None => content.dispose(),
+        }
+
+        async {}
+    });
+    parent.effect(fut);
+    Ok(())
 }
@@ -276,9 +276,7 @@ impl<S: Clone + 'static> App<S> {
     /// //}
     /// ```
     pub fn mount_with(&self, parent: &web_sys::Node, cb: impl Fn(&Self) -> Dom) {
-        let res = cb(self);
-        parent.append_child(&res.inner_element()).unwrap();
-        // We leak the root node to avoid callbacks and futures being dropped
-        std::mem::forget(res);
+        // The root node is leaked to avoid callbacks and futures being dropped
+        crate::mount_to(cb(self), parent).unwrap();
     }
 }
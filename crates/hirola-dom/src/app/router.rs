@@ -1,3 +1,4 @@
+use hirola_core::owner::Owner;
 use hirola_core::prelude::signal::{Mutable, MutableSignalCloned, SignalExt};
 use hirola_core::prelude::*;
 use std::collections::HashMap;
@@ -307,6 +308,8 @@ impl<S: Clone + 'static> Router<S> {
         let app = app.clone();
         let node = parent.clone();
         let not_found = self.not_found.clone();
+        let owner = Owner::current();
+        let mut page: Option<Owner> = None;
         let wait_for_next_route = route
             .signal_cloned()
             .map(move |route_match| {
@@ -316,10 +319,21 @@ impl<S: Clone + 'static> Router<S> {
                     Err(_) => &not_found,
                 };
 
-                let builder = page_fn(&app);
+                // Leaving a page runs the cleanups registered while it was built
+                if let Some(previous) = page.take() {
+                    previous.dispose();
+                }
+                let content = Owner::new(owner.as_ref());
+                let builder = content.run(|| page_fn(&app));
                 let dom = Dom::fragment();
-                dom.append_child(&builder);
-                node.replace_children_with(&dom);
+                match dom
+                    .append_child(&builder)
+                    .and_then(|_| node.replace_children_with(&dom))
+                {
+                    Ok(()) => content.mount(),
+                    Err(error) => node.report_error(error),
+                }
+                page = Some(content);
                 let window = web_sys::window().unwrap();
                 window
                     .history()
@@ -1,8 +1,11 @@
 pub mod prelude {
     use super::*;
     pub use attr_bind::*;
+    pub use attr_class::*;
     pub use attr_mixin::*;
     pub use attr_on::*;
+    pub use attr_prop::*;
+    pub use attr_style::*;
     pub use attr_use::*;
 }
 
@@ -59,7 +62,8 @@ pub mod attr_use {
             let node = dom.clone();
             let attr = signal.0;
             let future = SignalExt::dedupe_map(signal.1, move |value| {
-                GenericNode::set_attribute(&node, &attr, &value.to_string());
+                GenericNode::set_attribute(&node, &attr, &value.to_string())
+                    .unwrap_or_else(|error| node.report_error(error));
             })
             .to_future();
 
@@ -71,12 +75,23 @@ pub mod attr_use {
 pub mod attr_on {
     pub struct OnEffect;
 
+    use discard::DiscardOnDrop;
     use hirola_core::{
+        callback::{AsyncCallback, Overlap},
         effect::{EffectAttribute, SideEffect},
         generic_node::{EventListener, GenericNode},
+        prelude::CancelableFutureHandle,
+        templating::error_boundary,
+    };
+    use std::{
+        cell::{Cell, RefCell},
+        collections::HashMap,
+        rc::Rc,
     };
     use web_sys::Event;
 
+    use crate::{spawn, Dom};
+
     pub use crate::types::DomEvent::*;
     impl<
             D: EffectAttribute<Handler = OnEffect>,
@@ -88,24 +103,79 @@ pub mod attr_on {
             node.event(&attr.read_as_attr(), effect)
         }
     }
+
+    /// Spawns the future returned by the handler on every event.
+    ///
+    /// The running futures are kept by the listener, so they are cancelled once it is removed,
+    /// eg. when the content it was registered in is removed or the node is discarded.
+    impl<D: EffectAttribute<Handler = OnEffect>, F: AsyncFn(Event) + 'static>
+        SideEffect<D, AsyncCallback<F>, Dom> for OnEffect
+    {
+        fn effect(&self, node: &Dom, attr: D, callback: AsyncCallback<F>) {
+            let AsyncCallback { handler, overlap } = callback;
+            let handler = Rc::new(handler);
+            let running: Rc<RefCell<HashMap<usize, DiscardOnDrop<CancelableFutureHandle>>>> =
+                Default::default();
+            let next_id = Cell::new(0);
+            node.event(
+                &attr.read_as_attr(),
+                Box::new(move |event: Event| {
+                    match overlap {
+                        Overlap::Ignore if !running.borrow().is_empty() => return,
+                        Overlap::Cancel => running.borrow_mut().clear(),
+                        _ => {}
+                    }
+                    let id = next_id.replace(next_id.get() + 1);
+                    let handler = Rc::clone(&handler);
+                    let finished = Rc::downgrade(&running);
+                    let future = async move {
+                        handler(event).await;
+                        if let Some(running) = finished.upgrade() {
+                            running.borrow_mut().remove(&id);
+                        }
+                    };
+                    let handle = spawn(error_boundary::scoped(future));
+                    running.borrow_mut().insert(id, handle);
+                }) as Box<dyn Fn(Event)>,
+            )
+        }
+    }
 }
 
+/// Two-way bindings between form elements and signals.
+///
+/// The element is updated through its properties, so it stays in sync after the user edited it:
+///
+/// - `bind:value=&Mutable<T>` binds the text of an `<input>`, a `<textarea>` or the selected
+///   option of a `<select>`, and `bind:value=&MutableVec<T>` the options selected in a
+///   `<select multiple>`.
+/// - `bind:value=&ValueBinding<T, E>` binds the text like `&Mutable<T>`, and surfaces the text
+///   that can't be parsed, see [`attr_bind::ValueBinding`].
+/// - `bind:checked=&Mutable<bool>` binds a checkbox.
+/// - `bind:group=&Mutable<T>` checks the radio button whose `value` is equal to the value, and
+///   `bind:group=&MutableVec<T>` the checkboxes whose `value` is in the list.
 pub mod attr_bind {
-    pub struct Value;
-    use std::{
-        fmt::{Debug, Display},
-        str::FromStr,
-    };
+    use std::{convert::Infallible, fmt::Display, rc::Rc, str::FromStr};
 
     use hirola_core::{
         effect::{EffectAttribute, SideEffect},
         generic_node::{EventListener, GenericNode, NodeReference},
-        prelude::{signal::SignalExt, Mutable},
+        prelude::{
+            signal::{Signal, SignalExt},
+            signal_vec::SignalVecExt,
+            Mutable, MutableVec,
+        },
+        render::Error,
     };
-    use wasm_bindgen::JsCast;
-    use web_sys::{Event, HtmlInputElement};
+    use wasm_bindgen::{JsCast, JsValue};
+    use web_sys::{Event, HtmlOptionElement, HtmlSelectElement};
+
+    use crate::{node_ref::NodeRef, tag_of, Dom};
+
+    pub struct BindEffect;
+
+    pub struct Value;
 
-    use crate::{node_ref::NodeRef, Dom};
     impl EffectAttribute for Value {
         type Handler = BindEffect;
         fn read_as_attr(&self) -> String {
@@ -113,54 +183,450 @@ pub mod attr_bind {
         }
     }
 
-    pub struct BindEffect;
+    pub struct Checked;
 
-    impl EffectAttribute for Ref {
+    impl EffectAttribute for Checked {
         type Handler = BindEffect;
         fn read_as_attr(&self) -> String {
-            "ref".to_owned()
+            "checked".to_owned()
+        }
+    }
+
+    pub struct Group;
+
+    impl EffectAttribute for Group {
+        type Handler = BindEffect;
+        fn read_as_attr(&self) -> String {
+            "group".to_owned()
         }
     }
 
     pub struct Ref;
 
+    impl EffectAttribute for Ref {
+        type Handler = BindEffect;
+        fn read_as_attr(&self) -> String {
+            "ref".to_owned()
+        }
+    }
+
     impl SideEffect<Ref, NodeRef, Dom> for BindEffect {
         fn effect(&self, node: &Dom, _attr: Ref, value: NodeRef) {
             NodeReference::set(&value, node.clone());
         }
     }
 
-    impl<
-            T: Clone + Display + PartialEq + 'static + FromStr,
-            D: EffectAttribute<Handler = BindEffect>,
-            N: GenericNode + EventListener<Box<dyn Fn(Event)>>,
-        > SideEffect<D, &Mutable<T>, N> for BindEffect
+    fn get_property(target: &JsValue, name: &str) -> JsValue {
+        js_sys::Reflect::get(target, &JsValue::from_str(name)).unwrap_or(JsValue::UNDEFINED)
+    }
+
+    /// The property `name` of the element `event` was dispatched to.
+    fn target_property(event: &Event, name: &str) -> JsValue {
+        event
+            .current_target()
+            .map_or(JsValue::UNDEFINED, |target| get_property(&target, name))
+    }
+
+    fn set_property(node: &Dom, name: &str, value: JsValue) {
+        node.set_property(name, &value)
+            .unwrap_or_else(|error| node.report_error(error))
+    }
+
+    fn listen(node: &Dom, event: &str, handler: impl Fn(Event) + 'static) {
+        node.event(event, Box::new(handler) as Box<dyn Fn(Event)>);
+    }
+
+    type Parse<T, E> = Rc<dyn Fn(&str) -> Result<T, E>>;
+
+    /// The target of a `bind:value` that parses the text of the element, surfacing the text that
+    /// can't be parsed instead of panicking.
+    ///
+    /// While the text is invalid, the element keeps showing it, the value keeps the last valid one,
+    /// and the parse error is available through [`ValueBinding::error`]:
+    ///
+    /// ```rust,ignore
+    /// let age = ValueBinding::new(&Mutable::new(18u32));
+    /// html! {
+    ///     <input type="number" bind:value=&age/>
+    ///     <p class="error">{age.error().map(|error| error.map(|error| error.to_string()))}</p>
+    /// }
+    /// ```
+    ///
+    /// `bind:value=&Mutable<T>` behaves like `bind:value=&ValueBinding::new(..)`, ignoring the
+    /// errors.
+    pub struct ValueBinding<T, E> {
+        value: Mutable<T>,
+        error: Mutable<Option<E>>,
+        parse: Parse<T, E>,
+        format: Rc<dyn Fn(&T) -> String>,
+    }
+
+    impl<T, E> Clone for ValueBinding<T, E> {
+        fn clone(&self) -> Self {
+            ValueBinding {
+                value: self.value.clone(),
+                error: self.error.clone(),
+                parse: Rc::clone(&self.parse),
+                format: Rc::clone(&self.format),
+            }
+        }
+    }
+
+    impl<T: FromStr + Display + 'static> ValueBinding<T, T::Err> {
+        /// Parses the text with [`FromStr`] and formats the value with [`Display`].
+        pub fn new(value: &Mutable<T>) -> Self {
+            Self::with(value, str::parse, ToString::to_string)
+        }
+    }
+
+    impl<T: FromStr + Display + 'static> ValueBinding<Option<T>, Infallible> {
+        /// Sets the value to `None` while the text can't be parsed, eg. when it is empty.
+        pub fn optional(value: &Mutable<Option<T>>) -> Self {
+            Self::with(
+                value,
+                |text| Ok(text.parse().ok()),
+                |value| value.as_ref().map(ToString::to_string).unwrap_or_default(),
+            )
+        }
+    }
+
+    impl<T, E> ValueBinding<T, E> {
+        /// Parses the text with `parse` and formats the value with `format`.
+        pub fn with(
+            value: &Mutable<T>,
+            parse: impl Fn(&str) -> Result<T, E> + 'static,
+            format: impl Fn(&T) -> String + 'static,
+        ) -> Self {
+            ValueBinding {
+                value: value.clone(),
+                error: Mutable::new(None),
+                parse: Rc::new(parse),
+                format: Rc::new(format),
+            }
+        }
+
+        pub fn value(&self) -> &Mutable<T> {
+            &self.value
+        }
+
+        /// The error of parsing the text, while it is invalid.
+        pub fn error(&self) -> impl Signal<Item = Option<E>>
+        where
+            E: Clone,
+        {
+            self.error.signal_cloned()
+        }
+
+        pub fn get_error(&self) -> Option<E>
+        where
+            E: Clone,
+        {
+            self.error.get_cloned()
+        }
+    }
+
+    impl<T, E> SideEffect<Value, &ValueBinding<T, E>, Dom> for BindEffect
     where
-        <T as FromStr>::Err: Debug,
+        T: Clone + PartialEq + 'static,
+        E: 'static,
     {
-        fn effect(&self, node: &N, attr: D, value: &Mutable<T>) {
-            let attr = attr.read_as_attr().to_owned();
+        fn effect(&self, node: &Dom, _attr: Value, binding: &ValueBinding<T, E>) {
             let dom = node.clone();
-            let future = SignalExt::dedupe_map(value.signal_cloned(), move |value| {
-                GenericNode::set_attribute(&dom, &attr, &format!("{}", value));
-            })
-            .to_future();
-            let value = value.clone();
-            let handler = move |e: Event| {
-                let input = e
-                    .current_target()
-                    .unwrap()
-                    .dyn_into::<HtmlInputElement>()
-                    .unwrap();
-                let new_value = input.value();
-                value.set(new_value.parse().unwrap());
+            let sync = binding.clone();
+            let future = binding.value.signal_cloned().for_each(move |value| {
+                // Keeps the text the user typed when it is already this value, eg. `1.50` for `1.5`
+                let text = get_property(dom.as_ref(), "value").as_string();
+                if text.and_then(|text| (sync.parse)(&text).ok()).as_ref() != Some(&value) {
+                    set_property(&dom, "value", (sync.format)(&value).into());
+                    sync.error.set(None);
+                }
+                async {}
+            });
+            let binding = binding.clone();
+            listen(node, "input", move |event| {
+                let Some(text) = target_property(&event, "value").as_string() else {
+                    return;
+                };
+                match (binding.parse)(&text) {
+                    Ok(value) => {
+                        binding.error.set(None);
+                        binding.value.set(value);
+                    }
+                    Err(error) => binding.error.set(Some(error)),
+                }
+            });
+            node.effect(future);
+        }
+    }
+
+    impl<T> SideEffect<Value, &Mutable<T>, Dom> for BindEffect
+    where
+        T: Clone + Display + PartialEq + FromStr + 'static,
+    {
+        fn effect(&self, node: &Dom, attr: Value, value: &Mutable<T>) {
+            self.effect(node, attr, &ValueBinding::new(value))
+        }
+    }
+
+    fn options(select: &HtmlSelectElement) -> impl Iterator<Item = HtmlOptionElement> + '_ {
+        (0..select.length()).filter_map(|index| select.item(index)?.dyn_into().ok())
+    }
+
+    impl<T> SideEffect<Value, &MutableVec<T>, Dom> for BindEffect
+    where
+        T: Clone + Display + FromStr + 'static,
+    {
+        fn effect(&self, node: &Dom, _attr: Value, values: &MutableVec<T>) {
+            let Ok(select) = node.inner_element().dyn_into::<HtmlSelectElement>() else {
+                return node.report_error(Error::FailedCast {
+                    expected: "HtmlSelectElement",
+                    tag: tag_of(&node.inner_element()),
+                });
             };
-            node.event("input", Box::new(handler));
+            let future = values
+                .signal_vec_cloned()
+                .to_signal_cloned()
+                .for_each(move |values| {
+                    let values: Vec<String> = values.iter().map(ToString::to_string).collect();
+                    for option in options(&select) {
+                        option.set_selected(values.contains(&option.value()));
+                    }
+                    async {}
+                });
+            let values = values.clone();
+            listen(node, "input", move |event| {
+                let Some(select) = event
+                    .current_target()
+                    .and_then(|target| target.dyn_into::<HtmlSelectElement>().ok())
+                else {
+                    return;
+                };
+                let selected = options(&select)
+                    .filter(HtmlOptionElement::selected)
+                    .filter_map(|option| option.value().parse().ok())
+                    .collect();
+                values.lock_mut().replace_cloned(selected);
+            });
+            node.effect(future);
+        }
+    }
+
+    impl SideEffect<Checked, &Mutable<bool>, Dom> for BindEffect {
+        fn effect(&self, node: &Dom, _attr: Checked, checked: &Mutable<bool>) {
+            let dom = node.clone();
+            let future = checked.signal().for_each(move |checked| {
+                set_property(&dom, "checked", checked.into());
+                async {}
+            });
+            let checked = checked.clone();
+            listen(node, "change", move |event| {
+                checked.set(target_property(&event, "checked").is_truthy());
+            });
+            node.effect(future);
+        }
+    }
+
+    impl<T> SideEffect<Group, &Mutable<T>, Dom> for BindEffect
+    where
+        T: Clone + Display + FromStr + 'static,
+    {
+        fn effect(&self, node: &Dom, _attr: Group, value: &Mutable<T>) {
+            let dom = node.clone();
+            let future = value.signal_cloned().for_each(move |value| {
+                let own = get_property(dom.as_ref(), "value").as_string();
+                set_property(&dom, "checked", (own == Some(value.to_string())).into());
+                async {}
+            });
+            let value = value.clone();
+            listen(node, "change", move |event| {
+                if target_property(&event, "checked").is_truthy() {
+                    let own = target_property(&event, "value").as_string();
+                    if let Some(own) = own.and_then(|own| own.parse().ok()) {
+                        value.set(own);
+                    }
+                }
+            });
+            node.effect(future);
+        }
+    }
+
+    impl<T> SideEffect<Group, &MutableVec<T>, Dom> for BindEffect
+    where
+        T: Clone + Display + FromStr + 'static,
+    {
+        fn effect(&self, node: &Dom, _attr: Group, values: &MutableVec<T>) {
+            let dom = node.clone();
+            let future = values
+                .signal_vec_cloned()
+                .to_signal_cloned()
+                .for_each(move |values| {
+                    let own = get_property(dom.as_ref(), "value").as_string();
+                    let checked = values.iter().any(|value| Some(value.to_string()) == own);
+                    set_property(&dom, "checked", checked.into());
+                    async {}
+                });
+            let values = values.clone();
+            listen(node, "change", move |event| {
+                let Some(own) = target_property(&event, "value").as_string() else {
+                    return;
+                };
+                let checked = target_property(&event, "checked").is_truthy();
+                let mut values = values.lock_mut();
+                let position = values.iter().position(|value| value.to_string() == own);
+                match (checked, position) {
+                    (true, None) => {
+                        if let Ok(value) = own.parse() {
+                            values.push_cloned(value);
+                        }
+                    }
+                    (false, Some(index)) => {
+                        values.remove(index);
+                    }
+                    _ => {}
+                }
+            });
             node.effect(future);
         }
     }
 }
 
+/// `prop:name=value` sets the JavaScript property `name` of the element instead of its attribute,
+/// eg. `prop:indeterminate=true` or `prop:srcObject=JsValue::from(stream)`.
+///
+/// Signals are not deduplicated: setting a `Mutable` to its current value sets the property
+/// again, eg. to reset `value` after the user edited it.
+pub mod attr_prop {
+    use std::pin::Pin;
+
+    use hirola_core::{
+        effect::SideEffect,
+        generic_node::GenericNode,
+        impl_effect_for_signals,
+        prelude::{
+            signal::{Signal, SignalExt},
+            Mutable,
+        },
+    };
+    use wasm_bindgen::JsValue;
+
+    use crate::Dom;
+
+    pub use hirola_core::effect::{PropEffect, Property};
+
+    /// A value that a `prop:` directive can set, as it is or through a `Mutable` or a signal.
+    pub trait PropertyValue {
+        fn to_property(&self) -> JsValue;
+    }
+
+    macro_rules! impl_property_value {
+        ($($value_type:ty),*) => {
+            $(
+                impl PropertyValue for $value_type {
+                    fn to_property(&self) -> JsValue {
+                        JsValue::from(self.clone())
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_property_value!(bool, i8, i16, i32, u8, u16, u32, f32, f64, String, &str, JsValue);
+
+    impl PropertyValue for usize {
+        fn to_property(&self) -> JsValue {
+            JsValue::from(*self as f64)
+        }
+    }
+
+    impl PropertyValue for isize {
+        fn to_property(&self) -> JsValue {
+            JsValue::from(*self as f64)
+        }
+    }
+
+    /// `None` sets the property to `null`.
+    impl<T: PropertyValue> PropertyValue for Option<T> {
+        fn to_property(&self) -> JsValue {
+            self.as_ref()
+                .map_or(JsValue::NULL, PropertyValue::to_property)
+        }
+    }
+
+    fn set_property(node: &Dom, name: &str, value: &impl PropertyValue) {
+        node.set_property(name, &value.to_property())
+            .unwrap_or_else(|error| node.report_error(error))
+    }
+
+    macro_rules! impl_prop_effect {
+        ($($value_type:ty),*) => {
+            $(
+                impl SideEffect<Property, $value_type, Dom> for PropEffect {
+                    fn effect(&self, node: &Dom, attr: Property, effect: $value_type) {
+                        set_property(node, attr.0, &effect)
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_prop_effect!(bool, i8, i16, i32, u8, u16, u32, f32, f64, String, &str, JsValue);
+    impl_prop_effect!(usize, isize);
+
+    impl<T: PropertyValue> SideEffect<Property, Option<T>, Dom> for PropEffect {
+        fn effect(&self, node: &Dom, attr: Property, effect: Option<T>) {
+            set_property(node, attr.0, &effect)
+        }
+    }
+
+    impl<A: PropertyValue + Clone + 'static> SideEffect<Property, Mutable<A>, Dom> for PropEffect {
+        fn effect(&self, node: &Dom, attr: Property, effect: Mutable<A>) {
+            self.effect(node, attr, effect.signal_cloned())
+        }
+    }
+
+    fn set_property_signal(
+        node: &Dom,
+        name: &'static str,
+        signal: impl Signal<Item: PropertyValue> + 'static,
+    ) {
+        let dom = node.clone();
+        node.effect(signal.for_each(move |value| {
+            set_property(&dom, name, &value);
+            async {}
+        }));
+    }
+
+    impl_effect_for_signals!(
+        PropEffect,
+        Property,
+        Signal<Item: PropertyValue>,
+        set_property_signal,
+        Dom
+    );
+
+    /// Sets the property with a boxed signal, eg. one returned by [`SignalExt::boxed_local`]
+    impl<A: PropertyValue + 'static> SideEffect<Property, Pin<Box<dyn Signal<Item = A>>>, Dom>
+        for PropEffect
+    {
+        fn effect(&self, node: &Dom, attr: Property, effect: Pin<Box<dyn Signal<Item = A>>>) {
+            set_property_signal(node, attr.0, effect)
+        }
+    }
+}
+
+/// `class:name=value` toggles a single class through `classList`. The handler is shared with the
+/// other backends, so it lives in `hirola_core`.
+pub mod attr_class {
+    pub use hirola_core::effect::{ClassEffect, ClassName};
+}
+
+/// `style:property=value` sets a single property of the inline style through
+/// `CSSStyleDeclaration`. The handler is shared with the other backends, so it lives in
+/// `hirola_core`.
+pub mod attr_style {
+    pub use hirola_core::effect::{StyleEffect, StyleProperty, StyleValue};
+}
+
 pub mod attr_mixin {
     pub struct XEffect;
 
@@ -0,0 +1,337 @@
+//! Reusing server rendered markup instead of building new nodes.
+//!
+//! `html!` creates nodes in document order: an element is created before its children, and each
+//! child is built completely before the next one. While hydrating, [`Dom`](crate::Dom) hands out
+//! the nodes already under the hydration root in that same order instead of creating new ones.
+//! Appending a node that is already in place is then a no-op.
+//!
+//! When the server markup does not match, the client builds the node from scratch and puts it in
+//! place of the server node once it is appended. Debug builds log what was expected and found.
+use std::cell::RefCell;
+use std::fmt;
+
+use wasm_bindgen::JsCast;
+use web_sys::{Comment, Element, Node, Text};
+
+thread_local! {
+    static HYDRATOR: RefCell<Option<Hydrator>> = const { RefCell::new(None) };
+}
+
+/// The kind of node the client is about to create.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Expected<'a> {
+    Element(&'a str),
+    Text(&'a str),
+    Marker,
+}
+
+impl fmt::Display for Expected<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Element(tag) => write!(f, "<{tag}>"),
+            Expected::Text(text) => write!(f, "text {text:?}"),
+            Expected::Marker => write!(f, "a marker comment"),
+        }
+    }
+}
+
+struct Hydrator {
+    root: Node,
+    /// The next server node in document order.
+    next: Option<Node>,
+    /// Markers claimed before the cursor reached them, skipped once it does.
+    ///
+    /// Lists and switches append their marker before rendering their content, while the server
+    /// markup has the content first.
+    claimed_ahead: Vec<Node>,
+    /// A node built from scratch because the server markup did not match, with the server node it
+    /// replaces. Everything created until it is appended belongs to its subtree.
+    fresh: Option<(Node, Option<Node>)>,
+}
+
+impl Hydrator {
+    fn new(root: &Node) -> Self {
+        Hydrator {
+            root: root.clone(),
+            next: root.first_child(),
+            claimed_ahead: Vec::new(),
+            fresh: None,
+        }
+    }
+
+    /// The node after `node` in document order, without going outside of the root.
+    fn following(&self, node: &Node, into_children: bool) -> Option<Node> {
+        if into_children {
+            if let Some(child) = node.first_child() {
+                return Some(child);
+            }
+        }
+        let mut current = node.clone();
+        loop {
+            if current == self.root {
+                return None;
+            }
+            if let Some(sibling) = current.next_sibling() {
+                return Some(sibling);
+            }
+            current = current.parent_node()?;
+        }
+    }
+
+    /// The next server node that was not claimed yet.
+    fn peek(&mut self) -> Option<Node> {
+        while let Some(node) = self.next.clone() {
+            match self.claimed_ahead.iter().position(|n| n == &node) {
+                Some(index) => {
+                    self.claimed_ahead.remove(index);
+                    self.next = self.following(&node, false);
+                }
+                None => return Some(node),
+            }
+        }
+        None
+    }
+
+    /// Like [`Hydrator::peek`], but skips whitespace that `html!` would never produce.
+    fn peek_significant(&mut self) -> Option<Node> {
+        while let Some(node) = self.peek() {
+            match node.dyn_ref::<Text>() {
+                Some(text) if text.data().trim().is_empty() => {
+                    self.next = self.following(&node, false);
+                }
+                _ => return Some(node),
+            }
+        }
+        None
+    }
+
+    fn claim(&mut self, expected: Expected, create: impl FnOnce() -> Node) -> Node {
+        if self.fresh.is_some() {
+            return create();
+        }
+        match expected {
+            Expected::Element(tag) => match self.peek_significant() {
+                Some(node)
+                    if node
+                        .dyn_ref::<Element>()
+                        .is_some_and(|e| e.tag_name().eq_ignore_ascii_case(tag)) =>
+                {
+                    self.next = self.following(&node, true);
+                    node
+                }
+                server => self.mismatch(expected, server, create),
+            },
+            Expected::Text(text) => match self.peek() {
+                Some(node) if node.has_type::<Text>() => {
+                    let server = node.unchecked_ref::<Text>();
+                    let data = server.data();
+                    if data == text {
+                        self.next = self.following(&node, false);
+                    } else if !text.is_empty() && data.starts_with(text) {
+                        // The server joins adjacent text nodes
+                        let len = text.encode_utf16().count() as u32;
+                        self.next = server.split_text(len).ok().map(Into::into);
+                    } else if text.is_empty() {
+                        return self.missing(create);
+                    } else {
+                        report(&self.root, expected, Some(&node));
+                        server.set_data(text);
+                        self.next = self.following(&node, false);
+                    }
+                    node
+                }
+                // The server does not render empty text
+                _ if text.is_empty() => self.missing(create),
+                server => {
+                    report(&self.root, expected, server.as_ref());
+                    self.missing(create)
+                }
+            },
+            Expected::Marker => match self.peek_significant() {
+                Some(node) if is_marker(&node) => {
+                    self.next = self.following(&node, false);
+                    node
+                }
+                server => {
+                    let mut sibling = server.clone();
+                    while let Some(node) = sibling {
+                        if is_marker(&node) && !self.claimed_ahead.contains(&node) {
+                            self.claimed_ahead.push(node.clone());
+                            return node;
+                        }
+                        sibling = node.next_sibling();
+                    }
+                    report(&self.root, expected, server.as_ref());
+                    self.missing(create)
+                }
+            },
+        }
+    }
+
+    /// Builds a node whose server node does not match, skipping over the server subtree.
+    fn mismatch(
+        &mut self,
+        expected: Expected,
+        server: Option<Node>,
+        create: impl FnOnce() -> Node,
+    ) -> Node {
+        report(&self.root, expected, server.as_ref());
+        if let Some(server) = &server {
+            self.next = self.following(server, false);
+        }
+        let node = create();
+        self.fresh = Some((node.clone(), server));
+        node
+    }
+
+    /// Builds a node the server did not render.
+    fn missing(&mut self, create: impl FnOnce() -> Node) -> Node {
+        let node = create();
+        self.fresh = Some((node.clone(), None));
+        node
+    }
+
+    fn place(&mut self, parent: &Node, child: &Node) -> bool {
+        match &self.fresh {
+            Some((fresh, _)) if fresh == child => {
+                let (fresh, server) = self.fresh.take().unwrap();
+                if let Some(server) = server {
+                    if let Some(server_parent) = server.parent_node() {
+                        return server_parent.replace_child(&fresh, &server).is_ok();
+                    }
+                }
+                match self.peek() {
+                    Some(next) if next.parent_node().as_ref() == Some(parent) => {
+                        parent.insert_before(&fresh, Some(&next)).is_ok()
+                    }
+                    _ => false,
+                }
+            }
+            Some(_) => false,
+            None => self.root.contains(Some(child)),
+        }
+    }
+
+    /// Removes the server nodes that were never claimed.
+    fn finish(mut self) {
+        if let Some((_fresh, server)) = self.fresh.take() {
+            #[cfg(debug_assertions)]
+            log::warn!(
+                "hydration: a node built in place of {} was never appended",
+                server
+                    .as_ref()
+                    .map(describe)
+                    .unwrap_or_else(|| "nothing".into())
+            );
+            #[cfg(not(debug_assertions))]
+            let _ = server;
+        }
+        while let Some(node) = self.peek() {
+            #[cfg(debug_assertions)]
+            log::warn!(
+                "hydration: removing {} at {}, which the client did not render",
+                describe(&node),
+                path(&self.root, &node)
+            );
+            self.next = self.following(&node, false);
+            if let Some(parent) = node.parent_node() {
+                let _ = parent.remove_child(&node);
+            }
+        }
+    }
+}
+
+fn is_marker(node: &Node) -> bool {
+    node.dyn_ref::<Comment>()
+        .is_some_and(|comment| comment.data().is_empty())
+}
+
+#[cfg(debug_assertions)]
+fn report(root: &Node, expected: Expected, found: Option<&Node>) {
+    match found {
+        Some(node) => log::warn!(
+            "hydration mismatch at {}: expected {expected}, found {}",
+            path(root, node),
+            describe(node)
+        ),
+        None => log::warn!("hydration mismatch: expected {expected}, found nothing"),
+    }
+}
+
+#[cfg(not(debug_assertions))]
+fn report(_root: &Node, _expected: Expected, _found: Option<&Node>) {}
+
+#[cfg(debug_assertions)]
+fn describe(node: &Node) -> String {
+    if let Some(element) = node.dyn_ref::<Element>() {
+        format!("<{}>", element.tag_name().to_lowercase())
+    } else if let Some(text) = node.dyn_ref::<Text>() {
+        format!("text {:?}", text.data())
+    } else if let Some(comment) = node.dyn_ref::<Comment>() {
+        format!("<!--{}-->", comment.data())
+    } else {
+        node.node_name()
+    }
+}
+
+/// A css like path from the hydration root to `node`, for diagnostics.
+#[cfg(debug_assertions)]
+fn path(root: &Node, node: &Node) -> String {
+    let mut segments = Vec::new();
+    let mut current = node.clone();
+    while &current != root {
+        let mut index = 1;
+        let mut sibling = current.previous_sibling();
+        while let Some(node) = sibling {
+            index += 1;
+            sibling = node.previous_sibling();
+        }
+        segments.push(format!(
+            "{}:nth-child({index})",
+            current.node_name().to_lowercase()
+        ));
+        match current.parent_node() {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    segments.push(root.node_name().to_lowercase());
+    segments.reverse();
+    segments.join(" > ")
+}
+
+/// Whether nodes are currently being claimed from server markup.
+pub(crate) fn is_hydrating() -> bool {
+    HYDRATOR.with(|h| h.borrow().is_some())
+}
+
+/// Claims the next server node, or builds one with `create` when it does not match.
+///
+/// Returns `None` when not hydrating.
+pub(crate) fn claim(expected: Expected, create: impl FnOnce() -> Node) -> Option<Node> {
+    HYDRATOR.with(|h| {
+        h.borrow_mut()
+            .as_mut()
+            .map(|hydrator| hydrator.claim(expected, create))
+    })
+}
+
+/// Returns `true` if `child` is already in place under `parent`, and the DOM should be left alone.
+pub(crate) fn place(parent: &Node, child: &Node) -> bool {
+    HYDRATOR.with(|h| {
+        h.borrow_mut()
+            .as_mut()
+            .is_some_and(|hydrator| hydrator.place(parent, child))
+    })
+}
+
+/// Runs `f` while claiming nodes from the markup under `root`.
+pub(crate) fn hydrating<T>(root: &Node, f: impl FnOnce() -> T) -> T {
+    let previous = HYDRATOR.with(|h| h.borrow_mut().replace(Hydrator::new(root)));
+    let result = f();
+    let hydrator = HYDRATOR.with(|h| std::mem::replace(&mut *h.borrow_mut(), previous));
+    if let Some(hydrator) = hydrator {
+        hydrator.finish();
+    }
+    result
+}
@@ -1,7 +1,10 @@
 pub mod app;
 pub mod effects;
+mod hydration;
 pub mod mixins;
 pub mod node_ref;
+pub mod scheduler;
+pub mod styled;
 pub mod types;
 
 use core::fmt;
@@ -10,12 +13,19 @@ use hirola_core::prelude::cancelable_future;
 use hirola_core::render::Render;
 use hirola_core::{
     generic_node::{EventListener, GenericNode},
+    owner::Owner,
     prelude::CancelableFutureHandle,
     render::Error,
+    templating::error_boundary::{self, ErrorScope},
     BoxedLocal,
 };
-use std::rc::Rc;
-use std::{cell::RefCell, future::Future};
+use hydration::Expected;
+pub use scheduler::batch;
+use std::rc::{Rc, Weak};
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+};
 use wasm_bindgen::{prelude::*, JsCast};
 pub use web_sys::Event;
 use web_sys::{Element, Node, Text};
@@ -25,7 +35,8 @@ pub enum DomSideEffect {
     Mounted(CancelableFutureHandle),
 }
 
-pub type EventHandlers = Rc<RefCell<Vec<Closure<dyn Fn(Event)>>>>;
+/// The listeners registered on a node outside of an [`Owner`], with the event they listen to.
+pub type EventHandlers = Rc<RefCell<Vec<(String, Closure<dyn Fn(Event)>)>>>;
 
 /// Rendering backend for the DOM.
 ///
@@ -33,6 +44,11 @@ pub type EventHandlers = Rc<RefCell<Vec<Closure<dyn Fn(Event)>>>>;
 /// rendering backend for the frontend application. It allows interacting with DOM nodes directly
 /// and provides utility methods for type conversion and cloning.
 ///
+/// Effects and event listeners registered while building content owned by an [`Owner`], eg. a
+/// [`Match`](hirola_core::prelude::Match) arm or an [`Indexed`](hirola_core::prelude::Indexed)
+/// row, are cancelled and removed once that content is removed. The ones registered outside of an
+/// owner are kept on the node until [`Dom::discard`] is called.
+///
 /// _This API requires the following crate features to be activated: `dom`_
 ///
 #[derive(Clone)]
@@ -40,7 +56,15 @@ pub struct Dom {
     pub node: Node,
     pub side_effects: Rc<RefCell<Vec<DomSideEffect>>>,
     event_handlers: EventHandlers,
-    children: RefCell<Vec<Dom>>,
+    /// Shared by the clones of this node, so that they all see the same children.
+    children: Rc<RefCell<Vec<Dom>>>,
+    /// The `children` of the node this one was last inserted into, where the siblings inserted
+    /// next to it are recorded.
+    siblings: Rc<RefCell<Weak<RefCell<Vec<Dom>>>>>,
+    /// Errors raised while building this node, returned once it is rendered.
+    errors: Rc<RefCell<Vec<Error>>>,
+    /// The owner of the content rendered under this node by [`render_to`].
+    root: Rc<RefCell<Option<Owner>>>,
 }
 
 impl fmt::Debug for Dom {
@@ -48,8 +72,9 @@ impl fmt::Debug for Dom {
         f.debug_struct("Dom")
             .field("node", &self.node)
             .field("side_effects", &self.side_effects.borrow().len())
-            .field("event_handlers", &self.event_handlers.borrow())
+            .field("event_handlers", &self.event_handlers.borrow().len())
             .field("children", &self.children.borrow())
+            .field("errors", &self.errors.borrow().len())
             .finish()
     }
 }
@@ -70,24 +95,23 @@ impl Eq for Dom {}
 
 impl Default for Dom {
     fn default() -> Self {
-        Dom {
-            node: document().create_document_fragment().dyn_into().unwrap(),
-            side_effects: Default::default(),
-            event_handlers: Default::default(),
-            children: Default::default(),
-        }
+        Dom::fragment()
     }
 }
 
 impl Dom {
-    pub fn inner_html(&self) -> String {
-        {
-            let window = web_sys::window().unwrap();
-            let document = window.document().unwrap();
-            let element = document.create_element("div").unwrap();
-            crate::render_to(self.clone(), &element.clone().try_into().unwrap()).unwrap();
-            element.inner_html()
-        }
+    /// Renders this node into a detached `<div>` and returns its markup.
+    pub fn inner_html(&self) -> Result<String, Error> {
+        let element =
+            document()?
+                .create_element("div")
+                .map_err(|error| Error::InvalidOperation {
+                    operation: "create_element",
+                    tag: Some("div".to_owned()),
+                    source: Some(Box::new(JsError(error))),
+                })?;
+        crate::render_to(self.clone(), &element.clone().into())?;
+        Ok(element.inner_html())
     }
 
     pub fn new_from_node(node: &Node) -> Self {
@@ -95,17 +119,25 @@ impl Dom {
             node: node.clone(),
             side_effects: Rc::new(RefCell::new(vec![])),
             event_handlers: Rc::new(RefCell::new(vec![])),
-            children: RefCell::new(vec![]),
+            children: Rc::new(RefCell::new(vec![])),
+            siblings: Rc::new(RefCell::new(Weak::new())),
+            errors: Rc::new(RefCell::new(vec![])),
+            root: Rc::new(RefCell::new(None)),
         }
     }
 
+    /// Removes the event listeners and cancels the effects registered on this node and the nodes
+    /// appended to it outside of an [`Owner`]. The content rendered under it by [`render_to`] is
+    /// disposed.
     pub fn discard(&mut self) {
-        let _cleanup: Vec<()> = self
-            .event_handlers
-            .take()
-            .into_iter()
-            .map(|c| c.forget())
-            .collect();
+        if let Some(root) = self.root.take() {
+            root.dispose();
+        }
+        for (name, closure) in self.event_handlers.take() {
+            let _ = self
+                .node
+                .remove_event_listener_with_callback(&name, closure.as_ref().unchecked_ref());
+        }
         let _cleanup: Vec<()> = self
             .side_effects
             .take()
@@ -117,6 +149,9 @@ impl Dom {
                 }
             })
             .collect();
+        for mut child in self.children.take() {
+            child.discard();
+        }
     }
 }
 
@@ -179,126 +214,377 @@ impl From<Dom> for JsValue {
     }
 }
 
-fn document() -> web_sys::Document {
-    web_sys::window().unwrap().document().unwrap()
+fn document() -> Result<web_sys::Document, Error> {
+    web_sys::window()
+        .ok_or(Error::Missing("window"))?
+        .document()
+        .ok_or(Error::Missing("document"))
+}
+
+/// Stands in for a node that could not be created.
+fn placeholder() -> Node {
+    match document() {
+        Ok(document) => document.create_comment("").into(),
+        // Nothing can be rendered without a document, see `Dom::live`
+        Err(_) => JsValue::UNDEFINED.unchecked_into(),
+    }
+}
+
+/// A JavaScript exception thrown by a DOM operation, kept as the source of an [`Error`].
+#[derive(Debug)]
+pub struct JsError(pub JsValue);
+
+impl fmt::Display for JsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.as_string() {
+            Some(message) => f.write_str(&message),
+            None => write!(f, "{:?}", self.0),
+        }
+    }
+}
+
+impl std::error::Error for JsError {}
+
+/// The lowercase tag name of `node`, if it is an element.
+fn tag_of(node: &Node) -> Option<String> {
+    node.dyn_ref::<Element>()
+        .map(|element| element.tag_name().to_lowercase())
+}
+
+impl Dom {
+    /// Sets the JavaScript property `name` of the node, eg. `checked` or `srcObject`, as done by a
+    /// `prop:` directive. Unlike attributes, properties keep up with user edits and take any value.
+    pub fn set_property(&self, name: &str, value: &JsValue) -> Result<(), Error> {
+        let error = |source: Option<JsValue>| Error::InvalidAttribute {
+            tag: tag_of(&self.node),
+            name: format!("prop:{name}"),
+            source: source.map(|error| Box::new(JsError(error)) as _),
+        };
+        match js_sys::Reflect::set(self.live()?, &JsValue::from_str(name), value) {
+            Ok(true) => Ok(()),
+            // The property is read only
+            Ok(false) => Err(error(None)),
+            Err(source) => Err(error(Some(source))),
+        }
+    }
+
+    /// The node, unless it is the `undefined` [`placeholder`] used without a document, which
+    /// throws on most DOM calls.
+    fn live(&self) -> Result<&Node, Error> {
+        if self.node.is_undefined() {
+            return Err(Error::Missing("document"));
+        }
+        Ok(&self.node)
+    }
+
+    fn as_element(&self) -> Result<&Element, Error> {
+        self.node.dyn_ref::<Element>().ok_or(Error::FailedCast {
+            expected: "Element",
+            tag: None,
+        })
+    }
+
+    /// Records `child` as inserted before `reference`, or last.
+    fn insert_entry(&self, child: &Dom, reference: Option<&Dom>, moved: bool) {
+        let mut children = self.children.borrow_mut();
+        if moved {
+            children.retain(|existing| existing != child);
+        }
+        // References are usually markers that come after the inserted nodes
+        let index = reference
+            .and_then(|reference| children.iter().rposition(|existing| existing == reference))
+            .unwrap_or(children.len());
+        children.insert(index, child.clone());
+        child.adopted_by(&self.children);
+    }
+
+    fn adopted_by(&self, children: &Rc<RefCell<Vec<Dom>>>) {
+        *self.siblings.borrow_mut() = Rc::downgrade(children);
+    }
+
+    /// Claims the next server node while hydrating, or creates one. A node that could not be
+    /// created is replaced by a [`placeholder`] keeping the error.
+    fn create(
+        expected: Option<Expected>,
+        create: impl Fn(&web_sys::Document) -> Result<Node, Error>,
+    ) -> Self {
+        let failed = Cell::new(None);
+        let create = || match document().and_then(|document| create(&document)) {
+            Ok(node) => node,
+            Err(error) => {
+                failed.set(Some(error));
+                placeholder()
+            }
+        };
+        let node = match expected {
+            Some(expected) => hydration::claim(expected, create).unwrap_or_else(create),
+            None => create(),
+        };
+        let dom = Dom::new_from_node(&node);
+        if let Some(error) = failed.take() {
+            dom.keep_error(error);
+        }
+        dom
+    }
+
+    fn invalid(&self, operation: &'static str) -> impl FnOnce(JsValue) -> Error + '_ {
+        move |error| Error::InvalidOperation {
+            operation,
+            tag: tag_of(&self.node),
+            source: Some(Box::new(JsError(error))),
+        }
+    }
 }
 
 impl GenericNode for Dom {
     fn element(tag: &str) -> Self {
-        Dom::new_from_node(&document().create_element(tag).unwrap().dyn_into().unwrap())
+        Dom::create(Some(Expected::Element(tag)), |document| {
+            document
+                .create_element(tag)
+                .map(Node::from)
+                .map_err(|error| Error::InvalidOperation {
+                    operation: "create_element",
+                    tag: Some(tag.to_owned()),
+                    source: Some(Box::new(JsError(error))),
+                })
+        })
     }
 
     fn text_node(text: &str) -> Self {
-        Dom::new_from_node(&document().create_text_node(text).into())
+        Dom::create(Some(Expected::Text(text)), |document| {
+            Ok(document.create_text_node(text).into())
+        })
     }
 
     fn fragment() -> Self {
-        Dom::new_from_node(&document().create_document_fragment().dyn_into().unwrap())
+        Dom::create(None, |document| {
+            Ok(document.create_document_fragment().into())
+        })
     }
 
     fn marker() -> Self {
-        Dom::new_from_node(&document().create_comment("").into())
+        Dom::create(Some(Expected::Marker), |document| {
+            Ok(document.create_comment("").into())
+        })
     }
 
-    fn set_attribute(&self, name: &str, value: &str) {
-        self.node
-            .unchecked_ref::<Element>()
+    fn set_attribute(&self, name: &str, value: &str) -> Result<(), Error> {
+        self.as_element()?
             .set_attribute(name, value)
-            .unwrap();
+            .map_err(|error| Error::InvalidAttribute {
+                tag: tag_of(&self.node),
+                name: name.to_owned(),
+                source: Some(Box::new(JsError(error))),
+            })
     }
 
-    fn append_child(&self, child: &Self) {
-        match self.node.append_child(&child.node) {
-            Err(e) => log::warn!("Could not append child: {e:?}"),
-            _ => {
-                self.children.borrow_mut().push(child.clone());
-            }
-        }
+    fn remove_attribute(&self, name: &str) -> Result<(), Error> {
+        self.as_element()?
+            .remove_attribute(name)
+            .map_err(|error| Error::InvalidAttribute {
+                tag: tag_of(&self.node),
+                name: name.to_owned(),
+                source: Some(Box::new(JsError(error))),
+            })
     }
 
-    fn insert_child_before(&self, new_node: &Self, reference_node: Option<&Self>) {
-        match self
+    fn toggle_class(&self, name: &str, on: bool) -> Result<(), Error> {
+        self.as_element()?
+            .class_list()
+            .toggle_with_force(name, on)
+            .map(|_| ())
+            .map_err(|error| Error::InvalidAttribute {
+                tag: tag_of(&self.node),
+                name: format!("class:{name}"),
+                source: Some(Box::new(JsError(error))),
+            })
+    }
+
+    fn set_style_property(&self, property: &str, value: Option<&str>) -> Result<(), Error> {
+        let style = self
             .node
-            .insert_before(&new_node.node, reference_node.map(|n| &n.node))
-        {
-            Ok(_) => {}
-            Err(e) => log::warn!("Failed to insert child: {e:?}"),
+            .dyn_ref::<web_sys::HtmlElement>()
+            .ok_or(Error::FailedCast {
+                expected: "HtmlElement",
+                tag: tag_of(&self.node),
+            })?
+            .style();
+        match value {
+            Some(value) => style.set_property(property, value),
+            None => style.remove_property(property).map(|_| ()),
         }
+        .map_err(|error| Error::InvalidAttribute {
+            tag: tag_of(&self.node),
+            name: format!("style:{property}"),
+            source: Some(Box::new(JsError(error))),
+        })
+    }
+
+    fn append_child(&self, child: &Self) -> Result<(), Error> {
+        let (node, child_node) = (self.live()?, child.live()?);
+        if !hydration::place(node, child_node) {
+            node.append_child(child_node)
+                .map_err(self.invalid("append_child"))?;
+        }
+        self.children.borrow_mut().push(child.clone());
+        child.adopted_by(&self.children);
+        Ok(())
     }
 
-    fn remove_child(&self, child: &Self) {
-        match self.node.remove_child(&child.node) {
-            Ok(_) => {}
-            Err(e) => log::warn!("Failed to remove child: {e:?}"),
-        };
+    fn insert_child_before(
+        &self,
+        new_node: &Self,
+        reference_node: Option<&Self>,
+    ) -> Result<(), Error> {
+        let (node, child_node) = (self.live()?, new_node.live()?);
+        let moved = child_node.parent_node().as_ref() == Some(node);
+        if !hydration::place(node, child_node) {
+            node.insert_before(child_node, reference_node.map(|n| &n.node))
+                .map_err(self.invalid("insert_child_before"))?;
+        }
+        self.insert_entry(new_node, reference_node, moved);
+        Ok(())
     }
 
-    fn replace_child(&self, old: &Self, new: &Self) {
-        match self.node.replace_child(&old.node, &new.node) {
-            Ok(_) => {}
-            Err(e) => log::warn!("Failed to replace child: {e:?}"),
-        };
+    fn remove_child(&self, child: &Self) -> Result<(), Error> {
+        self.node
+            .remove_child(&child.node)
+            .map_err(self.invalid("remove_child"))?;
+        self.children
+            .borrow_mut()
+            .retain(|existing| existing != child);
+        Ok(())
     }
 
-    fn insert_sibling_before(&self, child: &Self) {
+    fn replace_child(&self, old: &Self, new: &Self) -> Result<(), Error> {
         self.node
-            .unchecked_ref::<Element>()
-            .before_with_node_1(&child.node)
-            .unwrap();
+            .replace_child(&new.node, &old.node)
+            .map_err(self.invalid("replace_child"))?;
+        let mut children = self.children.borrow_mut();
+        children.retain(|existing| existing != new);
+        match children.iter().position(|existing| existing == old) {
+            Some(index) => children[index] = new.clone(),
+            None => children.push(new.clone()),
+        }
+        new.adopted_by(&self.children);
+        Ok(())
+    }
+
+    fn insert_sibling_before(&self, child: &Self) -> Result<(), Error> {
+        self.as_element()?
+            .before_with_node_1(child.live()?)
+            .map_err(self.invalid("insert_sibling_before"))?;
+        let siblings = self.siblings.borrow().upgrade();
+        if let Some(siblings) = siblings {
+            {
+                let mut siblings = siblings.borrow_mut();
+                siblings.retain(|existing| existing != child);
+                let index = siblings
+                    .iter()
+                    .position(|existing| existing == self)
+                    .unwrap_or(siblings.len());
+                siblings.insert(index, child.clone());
+            }
+            child.adopted_by(&siblings);
+        }
+        Ok(())
     }
 
     fn parent_node(&self) -> Option<Self> {
-        let n = self.node.parent_node().unwrap();
-        Some(Dom::new_from_node(&n))
+        self.live()
+            .ok()?
+            .parent_node()
+            .map(|node| Dom::new_from_node(&node))
     }
 
     fn next_sibling(&self) -> Option<Self> {
-        self.node
+        self.live()
+            .ok()?
             .next_sibling()
             .map(|node| Dom::new_from_node(&node))
     }
 
-    fn remove_self(&self) {
-        self.node.unchecked_ref::<Element>().remove();
+    fn remove_self(&self) -> Result<(), Error> {
+        if let Some(parent) = self.live()?.parent_node() {
+            parent
+                .remove_child(&self.node)
+                .map_err(self.invalid("remove_self"))?;
+        }
+        Ok(())
     }
 
-    fn update_inner_text(&self, text: &str) {
-        self.node
-            .dyn_ref::<Text>()
-            .unwrap()
-            .set_text_content(Some(text));
+    fn update_inner_text(&self, text: &str) -> Result<(), Error> {
+        let node = self.node.dyn_ref::<Text>().ok_or(Error::FailedCast {
+            expected: "Text",
+            tag: tag_of(&self.node),
+        })?;
+        node.set_text_content(Some(text));
+        Ok(())
+    }
+
+    fn replace_children_with(&self, node: &Self) -> Result<(), Error> {
+        self.as_element()?
+            .replace_children_with_node_1(&node.inner_element());
+        *self.children.borrow_mut() = vec![node.clone()];
+        node.adopted_by(&self.children);
+        Ok(())
     }
-    fn replace_children_with(&self, node: &Self) {
-        let element = self.node.unchecked_ref::<Element>();
-        element.replace_children_with_node_1(&node.inner_element())
+
+    /// Errors raised before the node is in the document, eg. while `html!` builds it, are kept
+    /// for whoever renders it. The others are logged.
+    fn report_error(&self, error: Error) {
+        let Err(error) = error_boundary::catch(error) else {
+            return;
+        };
+        if !self.live().is_ok_and(Node::is_connected) {
+            return self.keep_error(error);
+        }
+        let mut message = error.to_string();
+        let mut source = std::error::Error::source(&error);
+        while let Some(error) = source {
+            message.push_str(&format!(": {error}"));
+            source = error.source();
+        }
+        log::error!("{message}");
+    }
+
+    fn keep_error(&self, error: Error) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    fn take_error(&self) -> Option<Error> {
+        let mut error = self.errors.take().into_iter().next();
+        for child in self.children.borrow().iter() {
+            let kept = child.take_error();
+            error = error.or(kept);
+        }
+        error
     }
 
+    /// Cancelled once the content it is registered in is removed, see [`Dom`].
     fn effect(&self, future: impl std::future::Future<Output = ()> + 'static) {
-        self.side_effects
-            .borrow_mut()
-            .push(DomSideEffect::Mounted(DiscardOnDrop::leak(spawn(future))));
+        let handle = DiscardOnDrop::leak(spawn_hydrating(error_boundary::scoped(future)));
+        match Owner::current() {
+            Some(owner) => owner.on_cleanup(move || handle.discard()),
+            None => self
+                .side_effects
+                .borrow_mut()
+                .push(DomSideEffect::Mounted(handle)),
+        }
     }
 
     fn children(&self) -> RefCell<Vec<Self>> {
-        self.children.clone()
+        RefCell::new(self.children.borrow().clone())
     }
 }
 
 /// Mounts a [`Dom`] and runs it forever
 /// See also [`render`] with `parent` being the `<body>` tag.
 pub fn mount(dom: Dom) -> Result<(), Error> {
-    let window = web_sys::window().ok_or(Error::DomError(Box::new("could not acquire window")))?;
-    let document = window
-        .document()
-        .ok_or(Error::DomError(Box::new("could not acquire document")))?;
-
-    mount_to(
-        dom,
-        &document
-            .body()
-            .ok_or(Error::DomError(Box::new("could not acquire body")))?
-            .into(),
-    )?;
+    let window = web_sys::window().ok_or(Error::Missing("window"))?;
+    let document = window.document().ok_or(Error::Missing("document"))?;
+
+    mount_to(dom, &document.body().ok_or(Error::Missing("body"))?.into())?;
     Ok(())
 }
 
@@ -306,40 +592,100 @@ pub fn mount(dom: Dom) -> Result<(), Error> {
 /// For rendering under the `<body>` tag, use [`render()`] instead.
 
 pub fn mount_to(dom: Dom, parent: &web_sys::Node) -> Result<(), Error> {
-    let parent = Dom::new_from_node(parent);
-    parent.append_child(&dom);
+    let parent = render_to(dom, parent)?;
     std::mem::forget(parent);
     Ok(())
 }
 
+/// Hydrates the server rendered markup under the `<body>` tag.
+/// Alias for [`hydrate_to`] with `parent` being the `<body>` tag.
+pub fn hydrate(f: impl FnOnce() -> Dom) -> Result<Dom, Error> {
+    let window = web_sys::window().ok_or(Error::Missing("window"))?;
+    let document = window.document().ok_or(Error::Missing("document"))?;
+    hydrate_to(f, &document.body().ok_or(Error::Missing("body"))?.into())
+}
+
+/// Hydrates the server rendered markup under a `parent` node.
+///
+/// Instead of creating new nodes, the [`Dom`] built by `f` takes over the nodes already under
+/// `parent`, in the order `html!` would create them. Event listeners, `bind:` effects and signal
+/// subscriptions are attached to the existing nodes, and the initial value of every effect is
+/// applied before this returns.
+///
+/// Where the markup does not match what the client renders, the client's nodes replace the
+/// server's. Debug builds log every mismatch along with its path from `parent`.
+///
+/// A [`Suspense`](hirola_core::prelude::Suspense) starts with its loading state on the client, so
+/// content resolved by the server is replaced until the client's own future resolves.
+pub fn hydrate_to(f: impl FnOnce() -> Dom, parent: &web_sys::Node) -> Result<Dom, Error> {
+    document()?;
+    hydration::hydrating(parent, || {
+        let dom = f();
+        render_to(dom, parent)
+    })
+}
+
 /// Render a [`Dom`] into the DOM.
 /// Alias for [`render_to`] with `parent` being the `<body>` tag.
 pub fn render(dom: Dom) -> Result<Dom, Error> {
-    let window = web_sys::window().unwrap();
-    let document = window.document().unwrap();
+    let window = web_sys::window().ok_or(Error::Missing("window"))?;
+    let document = window.document().ok_or(Error::Missing("document"))?;
 
-    render_to(dom, &document.body().unwrap())
+    render_to(dom, &document.body().ok_or(Error::Missing("body"))?.into())
 }
 
 /// Render a [`Dom`] under a `parent` node.
 /// For rendering under the `<body>` tag, use [`render()`] instead.
-
+///
+/// The content built outside of any [`Owner`] is mounted once it is inserted, and disposed when the
+/// returned node is [discarded](Dom::discard), see [`Owner::take_detached`].
 pub fn render_to(dom: Dom, parent: &web_sys::Node) -> Result<Dom, Error> {
+    document()?;
+    styled::install();
+    let root = Owner::take_detached();
     let parent = Dom::new_from_node(parent);
-    parent.append_child(&dom);
+    if let Err(error) = parent.append_built(&dom) {
+        root.dispose();
+        return Err(error);
+    }
+    root.mount();
+    *parent.root.borrow_mut() = Some(root);
     Ok(parent)
 }
 
 impl<F: Fn(web_sys::Event) + 'static> EventListener<F> for Dom {
     fn event(&self, name: &str, handler: F) {
-        let closure: Closure<dyn Fn(web_sys::Event)> = Closure::wrap(Box::new(handler));
-        self.node
+        let scope = ErrorScope::current();
+        let closure: Closure<dyn Fn(web_sys::Event)> = Closure::wrap(Box::new(move |event| {
+            scope.run(|| handler(event));
+        }));
+        if let Err(error) = self
+            .node
             .add_event_listener_with_callback(name, closure.as_ref().unchecked_ref())
-            .unwrap();
-        self.event_handlers.borrow_mut().push(closure);
+        {
+            self.report_error(self.invalid("add_event_listener")(error));
+        }
+        match Owner::current() {
+            // The owner keeps the closure alive until the listener is removed
+            Some(owner) => {
+                let node = self.node.clone();
+                let name = name.to_owned();
+                owner.on_cleanup(move || {
+                    let _ = node.remove_event_listener_with_callback(
+                        &name,
+                        closure.as_ref().unchecked_ref(),
+                    );
+                });
+            }
+            None => self
+                .event_handlers
+                .borrow_mut()
+                .push((name.to_owned(), closure)),
+        }
     }
 }
 
+/// Runs `future` on the effect queue, see [`scheduler`].
 #[inline]
 pub fn spawn<F>(future: F) -> DiscardOnDrop<CancelableFutureHandle>
 where
@@ -347,15 +693,25 @@ where
 {
     let (handle, future) = cancelable_future(future, || ());
 
-    wasm_bindgen_futures::spawn_local(future);
+    scheduler::spawn(future, false);
 
     handle
 }
 
+/// Like [`spawn`], but while hydrating the future is first polled in place, so that its initial
+/// value claims the server markup along with the rest of the tree.
+fn spawn_hydrating<F>(future: F) -> DiscardOnDrop<CancelableFutureHandle>
+where
+    F: Future<Output = ()> + 'static,
+{
+    let (handle, future) = cancelable_future(future, || ());
+    scheduler::spawn(future, hydration::is_hydrating());
+    handle
+}
+
 impl Render<Dom> for Dom {
     fn render_into(self: Box<Self>, parent: &Dom) -> Result<(), Error> {
-        parent.append_child(&self);
-        Ok(())
+        parent.append_built(&self)
     }
 }
 
@@ -18,7 +18,9 @@ use web_sys::Element;
 #[mixin]
 pub fn raw_text<'a>(text: &'a str) -> Box<dyn Fn(&Dom) + 'a> {
     let cb = move |dom: &Dom| {
-        dom.node.set_text_content(Some(text));
+        if let Ok(node) = dom.live() {
+            node.set_text_content(Some(text));
+        }
     };
     Box::new(cb)
 }
@@ -0,0 +1,209 @@
+//! Scheduling of the effects that update the DOM.
+//!
+//! By default each effect, like the ones `html!` registers for signals, `Match`, `Indexed` and
+//! `bind:`, is spawned on its own with `wasm_bindgen_futures` and updates the DOM as soon as its
+//! signals change.
+//!
+//! Once a [`Schedule`] is set, the effects spawned from then on run on a single queue instead.
+//! When signals change, the effects listening to them are queued and the queue is flushed once,
+//! running them in the order they were registered in. That way an effect always runs before the
+//! effects registered under the content it renders, which is the order
+//! [`Match`](hirola_core::prelude::Match) and [`Indexed`](hirola_core::prelude::Indexed) rely on.
+//! With [`Schedule::AnimationFrame`], updating many signals in a row only touches the DOM right
+//! before the browser paints:
+//!
+//! ```rust,no_run
+//! use hirola::dom::scheduler::{set_schedule, Schedule};
+//!
+//! set_schedule(Schedule::AnimationFrame);
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+    task::{Context, Wake, Waker},
+};
+
+use futures_util::task::noop_waker_ref;
+use hirola_core::BoxedLocal;
+use wasm_bindgen::{prelude::Closure, JsCast};
+
+/// When the queued effects are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// On the next microtask, right after the code that changed the signals.
+    Microtask,
+    /// Before the next repaint, using `requestAnimationFrame`.
+    AnimationFrame,
+}
+
+/// How many times a flush goes over the effects queued meanwhile, before leaving the rest to the
+/// next microtask. Keeps an effect that wakes itself on every poll from blocking the page.
+const MAX_PASSES: usize = 100;
+
+#[derive(Default)]
+struct Scheduler {
+    /// Pending tasks by the order they were spawned in.
+    tasks: RefCell<BTreeMap<usize, BoxedLocal<()>>>,
+    /// Tasks woken since they were last polled.
+    queue: RefCell<BTreeSet<usize>>,
+    next_id: Cell<usize>,
+    /// `None` until an app opts into the queue with [`set_schedule`].
+    schedule: Cell<Option<Schedule>>,
+    /// Whether a flush was requested from the browser and has not run yet.
+    requested: Cell<bool>,
+    flushing: Cell<bool>,
+    batching: Cell<usize>,
+}
+
+thread_local! {
+    static SCHEDULER: Scheduler = Scheduler::default();
+}
+
+/// Queues the effects spawned from now on, and flushes the queue as `schedule` says.
+pub fn set_schedule(schedule: Schedule) {
+    SCHEDULER.with(|scheduler| scheduler.schedule.set(Some(schedule)));
+}
+
+/// Runs `f`, then runs the effects queued meanwhile before returning, so that the DOM reflects
+/// every change made in `f` at once.
+///
+/// Only the effects spawned after a [`set_schedule`] are queued, the others still run on their
+/// own.
+///
+/// ```rust,no_run
+/// # use hirola::prelude::*;
+/// # let (first, last) = (Mutable::new(""), Mutable::new(""));
+/// hirola::dom::batch(|| {
+///     first.set("Jane");
+///     last.set("Doe");
+/// });
+/// ```
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    SCHEDULER.with(|scheduler| scheduler.batching.set(scheduler.batching.get() + 1));
+    let result = f();
+    let done = SCHEDULER.with(|scheduler| {
+        scheduler.batching.set(scheduler.batching.get() - 1);
+        scheduler.batching.get() == 0
+    });
+    if done {
+        flush();
+    }
+    result
+}
+
+struct TaskWaker(usize);
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        enqueue(self.0);
+    }
+}
+
+fn enqueue(id: usize) {
+    let request = SCHEDULER.with(|scheduler| {
+        scheduler.queue.borrow_mut().insert(id);
+        scheduler.batching.get() == 0
+            && !scheduler.flushing.get()
+            && !scheduler.requested.replace(true)
+    });
+    if request {
+        request_flush();
+    }
+}
+
+fn request_flush() {
+    let schedule = SCHEDULER.with(|scheduler| scheduler.schedule.get());
+    match schedule.unwrap_or(Schedule::Microtask) {
+        Schedule::Microtask => wasm_bindgen_futures::spawn_local(async { flush() }),
+        Schedule::AnimationFrame => {
+            let callback = Closure::once_into_js(flush);
+            let requested = web_sys::window()
+                .map(|window| window.request_animation_frame(callback.unchecked_ref()));
+            if !matches!(requested, Some(Ok(_))) {
+                wasm_bindgen_futures::spawn_local(async { flush() });
+            }
+        }
+    }
+}
+
+/// Polls the task `id` once, dropping it if it completed.
+fn poll(id: usize) {
+    let Some(mut future) = SCHEDULER.with(|scheduler| scheduler.tasks.borrow_mut().remove(&id))
+    else {
+        return;
+    };
+    let waker = Waker::from(Arc::new(TaskWaker(id)));
+    let mut cx = Context::from_waker(&waker);
+    if future.as_mut().poll(&mut cx).is_pending() {
+        SCHEDULER.with(|scheduler| scheduler.tasks.borrow_mut().insert(id, future));
+    }
+}
+
+/// Clears [`Scheduler::flushing`], even when an effect panics.
+struct Flushing;
+
+impl Drop for Flushing {
+    fn drop(&mut self) {
+        SCHEDULER.with(|scheduler| scheduler.flushing.set(false));
+    }
+}
+
+/// Runs the queued effects, and the ones they queue in turn, in the order they were spawned in.
+fn flush() {
+    let flushing = SCHEDULER.with(|scheduler| {
+        scheduler.requested.set(false);
+        scheduler.flushing.replace(true)
+    });
+    if flushing {
+        return;
+    }
+    let _flushing = Flushing;
+    for _ in 0..MAX_PASSES {
+        let queue = SCHEDULER.with(|scheduler| scheduler.queue.take());
+        if queue.is_empty() {
+            return;
+        }
+        for id in queue {
+            poll(id);
+        }
+    }
+    let request = SCHEDULER.with(|scheduler| {
+        !scheduler.queue.borrow().is_empty() && !scheduler.requested.replace(true)
+    });
+    if request {
+        wasm_bindgen_futures::spawn_local(async { flush() });
+    }
+}
+
+/// Queues `future` to be polled with the next flush. With `now`, it is polled right away instead.
+///
+/// Without a schedule, `future` is spawned with `wasm_bindgen_futures`.
+pub(crate) fn spawn(future: impl Future<Output = ()> + 'static, now: bool) {
+    if SCHEDULER.with(|scheduler| scheduler.schedule.get().is_none()) {
+        let mut future = Box::pin(future);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        if now && future.as_mut().poll(&mut cx).is_ready() {
+            return;
+        }
+        // Polling again on the executor registers the real waker
+        wasm_bindgen_futures::spawn_local(future);
+        return;
+    }
+    let id = SCHEDULER.with(|scheduler| {
+        let id = scheduler.next_id.get();
+        scheduler.next_id.set(id + 1);
+        scheduler.tasks.borrow_mut().insert(id, Box::pin(future));
+        id
+    });
+    if now {
+        poll(id);
+    } else {
+        enqueue(id);
+    }
+}
@@ -0,0 +1,61 @@
+//! The [`StyleBackend`] that inserts the rules of [`Styled`] components into a stylesheet.
+//!
+//! It is set by [`render_to`](crate::render_to) and the other entry points, so components
+//! styled before the first render get their rules inserted then.
+//!
+//! [`Styled`]: hirola_core::templating::styled::Styled
+use std::collections::HashSet;
+
+use hirola_core::templating::styled::{has_style_backend, set_style_backend, StyleBackend};
+use wasm_bindgen::JsCast;
+use web_sys::CssStyleSheet;
+
+/// Inserts the rules into a `<style>` element appended to the `<head>` on first use.
+#[derive(Debug, Default)]
+pub struct StyleSheet {
+    ids: HashSet<u64>,
+    sheet: Option<CssStyleSheet>,
+}
+
+impl StyleSheet {
+    fn sheet(&mut self) -> Option<&CssStyleSheet> {
+        if self.sheet.is_none() {
+            let document = web_sys::window()?.document()?;
+            let style = document.create_element("style").ok()?;
+            document.head()?.append_child(&style).ok()?;
+            self.sheet = style
+                .unchecked_into::<web_sys::HtmlStyleElement>()
+                .sheet()
+                .and_then(|sheet| sheet.dyn_into().ok());
+        }
+        self.sheet.as_ref()
+    }
+}
+
+impl StyleBackend for StyleSheet {
+    fn contains(&self, id: u64) -> bool {
+        self.ids.contains(&id)
+    }
+
+    fn insert(&mut self, id: u64, rules: Vec<String>) {
+        if !self.ids.insert(id) {
+            return;
+        }
+        let Some(sheet) = self.sheet() else {
+            return;
+        };
+        for rule in rules {
+            let index = sheet.css_rules().map_or(0, |rules| rules.length());
+            if let Err(error) = sheet.insert_rule_with_index(&rule, index) {
+                log::warn!("could not insert the style rule {rule}: {error:?}");
+            }
+        }
+    }
+}
+
+/// Sets a [`StyleSheet`] as the style backend, unless there is one already.
+pub fn install() {
+    if !has_style_backend() {
+        set_style_backend(StyleSheet::default());
+    }
+}
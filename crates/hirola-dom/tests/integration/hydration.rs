@@ -0,0 +1,119 @@
+use hirola_core::prelude::{signal::Mutable, signal_vec::MutableVec};
+use hirola_dom::dom_test_utils::next_tick_with;
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+
+use super::*;
+
+/// Fills the test container with `markup` as if it was rendered on the server.
+fn server_div(markup: &str) -> Element {
+    let div = test_div().unchecked_into::<Element>();
+    div.set_inner_html(markup);
+    div
+}
+
+#[wasm_bindgen_test]
+fn reuses_server_nodes() {
+    let div = server_div("<main><p>Hello World!</p></main>");
+    let p = document().query_selector("p").unwrap().unwrap();
+
+    let _ = hydrate_to(|| html! { <main><p>"Hello World!"</p></main> }, &div);
+
+    let hydrated = document().query_selector("p").unwrap().unwrap();
+    assert_eq!(hydrated, p);
+    assert_eq!(div.inner_html(), "<main><p>Hello World!</p></main>");
+}
+
+#[wasm_bindgen_test]
+fn attaches_listeners() {
+    let div = server_div("<button>Click</button>");
+    let clicks = Rc::new(Cell::new(0));
+    let counter = clicks.clone();
+
+    let _ = hydrate_to(
+        move || {
+            let counter = counter.clone();
+            html! { <button on:click=move |_| counter.set(counter.get() + 1)>"Click"</button> }
+        },
+        &div,
+    );
+
+    let button = document()
+        .query_selector("button")
+        .unwrap()
+        .unwrap()
+        .unchecked_into::<HtmlElement>();
+    button.click();
+    assert_eq!(clicks.get(), 1);
+}
+
+#[wasm_bindgen_test]
+fn splits_joined_text() {
+    let div = server_div("<p>Hello World</p>");
+    let name = Mutable::new("World".to_string());
+
+    let _ = hydrate_to(|| html! { <p>"Hello "{name.clone()}</p> }, &div);
+
+    let p = document().query_selector("p").unwrap().unwrap();
+    assert!(p.first_child().unwrap().next_sibling().is_some());
+
+    name.set("Hirola".to_string());
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "Hello Hirola");
+    });
+}
+
+#[wasm_bindgen_test]
+fn claims_list_items() {
+    let div = server_div("<ul><li>1</li><li>2</li><!----></ul>");
+    let first = document().query_selector("li").unwrap().unwrap();
+    let items = MutableVec::new_with_values(vec![1, 2]);
+
+    let _ = hydrate_to(
+        || {
+            html! {
+                <ul>
+                    <Keyed
+                        props={KeyedProps {
+                            iterable: items.signal_vec(),
+                            template: |item| html! { <li>{item.to_string()}</li> },
+                            key: |item| *item,
+                        }}
+                    />
+                </ul>
+            }
+        },
+        &div,
+    );
+
+    let ul = document().query_selector("ul").unwrap().unwrap();
+    assert_eq!(ul.inner_html(), "<li>1</li><li>2</li><!---->");
+    assert_eq!(document().query_selector("li").unwrap().unwrap(), first);
+
+    items.lock_mut().push(3);
+    next_tick_with(&ul, |ul| {
+        assert_eq!(ul.text_content().unwrap(), "123");
+    });
+}
+
+#[wasm_bindgen_test]
+fn replaces_mismatched_nodes() {
+    let div = server_div("<main><span>Server</span><p>Kept</p></main>");
+    let kept = document().query_selector("p").unwrap().unwrap();
+
+    let _ = hydrate_to(|| html! { <main><b>"Client"</b><p>"Kept"</p></main> }, &div);
+
+    assert_eq!(div.inner_html(), "<main><b>Client</b><p>Kept</p></main>");
+    assert_eq!(document().query_selector("p").unwrap().unwrap(), kept);
+}
+
+#[wasm_bindgen_test]
+fn removes_extra_server_nodes() {
+    let div = server_div("<p>One</p><p>Two</p>");
+
+    let _ = hydrate_to(|| html! { <p>"One"</p> }, &div);
+
+    assert_eq!(div.inner_html(), "<p>One</p>");
+}
@@ -1,198 +1,235 @@
-// use super::*;
-
-// #[wasm_bindgen_test]
-// fn append() {
-//     let count = Mutable::new(vec![1, 2]);
-
-//     let node = cloned!((count) => html! {
-//         <ul>
-//             <Keyed
-//                 props={KeyedProps {
-//                     iterable: count.handle(),
-//                     template: |item| html! {
-//                         <li>{ item }</li>
-//                     },
-//                     key: |item| *item,
-//                 }}
-//             />
-//         </ul>
-
-//     });
-
-//     render_to(|| node, &test_div());
-
-//     let p = document().query_selector("ul").unwrap().unwrap();
-
-//     assert_eq!(p.text_content().unwrap(), "12");
-
-//     count.set({
-//         let mut tmp = (*count.get()).clone();
-//         tmp.push(3);
-//         tmp
-//     });
-//     assert_eq!(p.text_content().unwrap(), "123");
-
-//     count.set(count.get()[1..].into());
-//     assert_eq!(p.text_content().unwrap(), "23");
-// }
-
-// #[wasm_bindgen_test]
-// fn swap_rows() {
-//     let count = Mutable::new(vec![1, 2, 3]);
-
-//     let node = cloned!((count) => html! {
-//         <ul>
-//             <Keyed
-//                 props={KeyedProps {
-//                     iterable: count.handle(),
-//                     template: |item| html! {
-//                         <li>{ item }</li>
-//                     },
-//                     key: |item| *item,
-//                 }}
-//             />
-//         </ul>
-//     });
-
-//     render_to(|| node, &test_div());
-
-//     let p = document().query_selector("ul").unwrap().unwrap();
-//     assert_eq!(p.text_content().unwrap(), "123");
-
-//     count.set({
-//         let mut tmp = (*count.get()).clone();
-//         tmp.swap(0, 2);
-//         tmp
-//     });
-//     assert_eq!(p.text_content().unwrap(), "321");
-
-//     count.set({
-//         let mut tmp = (*count.get()).clone();
-//         tmp.swap(0, 2);
-//         tmp
-//     });
-//     assert_eq!(p.text_content().unwrap(), "123");
-// }
-
-// #[wasm_bindgen_test]
-// fn delete_row() {
-//     let count = Mutable::new(vec![1, 2, 3]);
-
-//     let node = cloned!((count) => html! {
-//         <ul>
-//             <Keyed
-//                 props={KeyedProps {
-//                     iterable: count.handle(),
-//                     template: |item| html! {
-//                         <li>{ item }</li>
-//                     },
-//                     key: |item| *item,
-//                 }}
-//             />
-//         </ul>
-//     });
-
-//     render_to(|| node, &test_div());
-
-//     let p = document().query_selector("ul").unwrap().unwrap();
-//     assert_eq!(p.text_content().unwrap(), "123");
-
-//     count.set({
-//         let mut tmp = (*count.get()).clone();
-//         tmp.remove(1);
-//         tmp
-//     });
-//     assert_eq!(p.text_content().unwrap(), "13");
-// }
-
-// #[wasm_bindgen_test]
-// fn clear() {
-//     let count = Mutable::new(vec![1, 2, 3]);
-
-//     let node = cloned!((count) => html! {
-//         <ul>
-//             <Keyed
-//                 props={KeyedProps {
-//                     iterable: count.handle(),
-//                     template: |item| html! {
-//                         <li>{ item }</li>
-//                     },
-//                     key: |item| *item,
-//                 }}
-//             />
-//         </ul>
-//     });
-
-//     render_to(|| node, &test_div());
-
-//     let p = document().query_selector("ul").unwrap().unwrap();
-//     assert_eq!(p.text_content().unwrap(), "123");
-
-//     count.set(Vec::new());
-//     assert_eq!(p.text_content().unwrap(), "");
-// }
-
-// #[wasm_bindgen_test]
-// fn insert_front() {
-//     let count = Mutable::new(vec![1, 2, 3]);
-
-//     let node = cloned!((count) => html! {
-//         <ul>
-//             <Keyed
-//                 props={KeyedProps {
-//                     iterable: count.handle(),
-//                     template: |item| html! {
-//                         <li>{ item }</li>
-//                     },
-//                     key: |item| *item,
-//                 }}
-//             />
-//         </ul>
-//     });
-
-//     render_to(|| node, &test_div());
-
-//     let p = document().query_selector("ul").unwrap().unwrap();
-//     assert_eq!(p.text_content().unwrap(), "123");
-
-//     count.set({
-//         let mut tmp = (*count.get()).clone();
-//         tmp.insert(0, 4);
-//         tmp
-//     });
-//     assert_eq!(p.text_content().unwrap(), "4123");
-// }
-
-// #[wasm_bindgen_test]
-// fn nested_reactivity() {
-//     let count = Mutable::new(vec![1, 2, 3].into_iter().map(Mutable::new).collect());
-
-//     let node = cloned!((count) => html! {
-//         <ul>
-//             <Keyed
-//                 props={KeyedProps {
-//                     iterable: count.handle(),
-//                     template: |item| html! {
-//                         <li>{ item.get() }</li>
-//                     },
-//                     key: |item| *item.get(),
-//                 }}
-//             />
-//         </ul>
-//     });
-
-//     render_to(|| node, &test_div());
-
-//     let p = document().query_selector("ul").unwrap().unwrap();
-//     assert_eq!(p.text_content().unwrap(), "123");
-
-//     count.get()[0].set(4);
-//     assert_eq!(p.text_content().unwrap(), "423");
-
-//     count.set({
-//         let mut tmp = (*count.get()).clone();
-//         tmp.push(Mutable::new(5));
-//         tmp
-//     });
-//     assert_eq!(p.text_content().unwrap(), "4235");
-// }
+use hirola_core::prelude::{signal::Mutable, signal_vec::MutableVec};
+use hirola_dom::dom_test_utils::{next_tick, next_tick_with};
+
+use super::*;
+
+#[wasm_bindgen_test]
+fn append() {
+    let count = MutableVec::new_with_values(vec![1, 2]);
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec(),
+                    template: |item| html! { <li>{item.to_string()}</li> },
+                    key: |item| *item,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let p = document().query_selector("ul").unwrap().unwrap();
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "12");
+    });
+
+    count.lock_mut().push(3);
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+
+    let new_value = count.lock_ref()[1..].to_vec();
+    count.lock_mut().replace(new_value);
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "23");
+    });
+}
+
+#[wasm_bindgen_test]
+fn swap_rows() {
+    let count = MutableVec::new_with_values(vec![1, 2, 3]);
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec(),
+                    template: |item| html! { <li>{item.to_string()}</li> },
+                    key: |item| *item,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let p = document().query_selector("ul").unwrap().unwrap();
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+
+    count.lock_mut().swap(0, 2);
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "321");
+    });
+
+    count.lock_mut().swap(0, 2);
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+}
+
+#[wasm_bindgen_test]
+fn reorder_reuses_nodes() {
+    let count = MutableVec::new_with_values(vec![1, 2, 3]);
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec(),
+                    template: |item| html! { <li>{item.to_string()}</li> },
+                    key: |item| *item,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let first = Mutable::new(None);
+    let cloned = first.clone();
+    next_tick(move || {
+        let li = document().query_selector("li").unwrap().unwrap();
+        cloned.set(Some(li));
+    });
+
+    count.lock_mut().replace(vec![3, 1, 2]);
+    next_tick(move || {
+        let p = document().query_selector("ul").unwrap().unwrap();
+        assert_eq!(p.text_content().unwrap(), "312");
+        let li = p.query_selector("li:nth-child(2)").unwrap();
+        assert_eq!(first.get_cloned(), li);
+    });
+}
+
+#[wasm_bindgen_test]
+fn delete_row() {
+    let count = MutableVec::new_with_values(vec![1, 2, 3]);
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec(),
+                    template: |item| html! { <li>{item.to_string()}</li> },
+                    key: |item| *item,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let p = document().query_selector("ul").unwrap().unwrap();
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+
+    count.lock_mut().remove(1);
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "13");
+    });
+}
+
+#[wasm_bindgen_test]
+fn clear() {
+    let count = MutableVec::new_with_values(vec![1, 2, 3]);
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec(),
+                    template: |item: i32| html! { <li>{item.to_string()}</li> },
+                    key: |item| *item,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let p = document().query_selector("ul").unwrap().unwrap();
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+
+    count.lock_mut().clear();
+    next_tick_with(&p, |p| {
+        assert_eq!(p.text_content().unwrap(), "");
+    });
+}
+
+#[wasm_bindgen_test]
+fn insert_front() {
+    let count = MutableVec::new_with_values(vec![1, 2, 3]);
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec(),
+                    template: |item| html! { <li>{item.to_string()}</li> },
+                    key: |item| *item,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+    next_tick(|| {
+        let p = document().query_selector("ul").unwrap().unwrap();
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+
+    count.lock_mut().insert(0, 4);
+    next_tick(|| {
+        let p = document().query_selector("ul").unwrap().unwrap();
+        assert_eq!(p.text_content().unwrap(), "4123");
+    });
+}
+
+#[wasm_bindgen_test]
+fn nested_reactivity() {
+    let count = MutableVec::new_with_values(
+        vec![1u32, 2, 3]
+            .into_iter()
+            .map(|id| (id, Mutable::new(id)))
+            .collect(),
+    );
+
+    let node = html! {
+        <ul>
+            <Keyed
+                props={KeyedProps {
+                    iterable: count.signal_vec_cloned(),
+                    template: |(_, item)| html! { <li>{item}</li> },
+                    key: |(id, _)| *id,
+                }}
+            />
+        </ul>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    next_tick(|| {
+        let p = document().query_selector("ul").unwrap().unwrap();
+        assert_eq!(p.text_content().unwrap(), "123");
+    });
+
+    count.lock_ref()[0].1.set(4);
+    next_tick(|| {
+        let p = document().query_selector("ul").unwrap().unwrap();
+        assert_eq!(p.text_content().unwrap(), "423");
+    });
+
+    count.lock_mut().push_cloned((5, Mutable::new(5)));
+    next_tick(|| {
+        let p = document().query_selector("ul").unwrap().unwrap();
+        assert_eq!(p.text_content().unwrap(), "4235");
+    });
+}
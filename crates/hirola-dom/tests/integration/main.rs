@@ -1,3 +1,4 @@
+pub mod hydration;
 pub mod keyed;
 pub mod non_keyed;
 pub mod router;
@@ -8,6 +9,8 @@ use hirola::prelude::*;
 use hirola::signal::SignalExt;
 use hirola_dom::dom_test_utils::{next_tick, next_tick_with};
 use hirola_dom::node_ref::NodeRef;
+use hirola_dom::scheduler::{set_schedule, Schedule};
+use wasm_bindgen::JsCast;
 use wasm_bindgen_test::*;
 use web_sys::{Document, HtmlElement, Node, Window};
 
@@ -145,3 +148,517 @@ fn noderefs() {
 
     assert_eq!(Node::from(input_ref), noderef.get().unchecked_into());
 }
+
+#[derive(Clone, PartialEq)]
+enum Status {
+    Loading,
+    Ready(u32),
+    Failed,
+}
+
+#[wasm_bindgen_test]
+fn match_switches_on_arm_change() {
+    let status = Mutable::new(Status::Loading);
+    let count = Mutable::new(1);
+    let shown = count.clone();
+
+    let node = html! {
+        <div>
+            {match status.signal_cloned() as Signal {
+                Status::Loading => html! { <p>"Loading"</p> },
+                Status::Ready(_) => html! { <p>{shown.clone()}</p> },
+                Status::Failed => html! { <p>"Failed"</p> },
+            }}
+        </div>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let div = document()
+        .query_selector("div#test-container > div")
+        .unwrap()
+        .unwrap();
+    next_tick_with(&div, |div| {
+        assert_eq!(div.text_content().unwrap(), "Loading");
+    });
+
+    status.set(Status::Ready(1));
+    next_tick_with(&div, |div| {
+        assert_eq!(div.text_content().unwrap(), "1");
+    });
+
+    let p = Mutable::new(None);
+    let first = p.clone();
+    next_tick(move || {
+        first.set(document().query_selector("div#test-container p").unwrap());
+    });
+    count.set(2);
+    status.set(Status::Ready(2));
+    next_tick(move || {
+        let current = document().query_selector("div#test-container p").unwrap();
+        assert_eq!(current.as_ref().unwrap().text_content().unwrap(), "2");
+        assert_eq!(p.get_cloned(), current);
+    });
+
+    status.set(Status::Failed);
+    next_tick_with(&div, |div| {
+        assert_eq!(div.text_content().unwrap(), "Failed");
+    });
+}
+
+#[wasm_bindgen_test]
+fn signal_of_nodes_swaps_in_place() {
+    let show = Mutable::new(true);
+
+    let node = html! {
+        <div>
+            <span>"Before"</span>
+            {show.signal().map(|show| -> Dom {
+                if show {
+                    html! { <b>"Yes"</b> }
+                } else {
+                    html! { <i>"No"</i> }
+                }
+            })}
+            <span>"After"</span>
+        </div>
+    };
+
+    let _ = render_to(node, &test_div());
+
+    let div = document()
+        .query_selector("div#test-container > div")
+        .unwrap()
+        .unwrap();
+    assert_eq!(div.text_content().unwrap(), "BeforeYesAfter");
+
+    show.set(false);
+    next_tick_with(&div, |div| {
+        assert_eq!(
+            div.inner_html(),
+            "<span>Before</span><!----><i>No</i><!----><span>After</span>"
+        );
+    });
+}
+
+#[wasm_bindgen_test]
+fn error_boundary_catches_handler_errors_and_retries() {
+    let boundary = ErrorBoundary::new(
+        || {
+            let node: Dom = html! { <p>"Content"</p> };
+            let failing = node.clone();
+            let fail = move |_| failing.report_error(hirola_core::render::Error::Missing("data"));
+            html! { <div>{node}<button id="fail" on:click=fail>"Fail"</button></div> }
+        },
+        |error, retry| {
+            html! {
+                <div>
+                    <p>{error.to_string()}</p>
+                    <button id="retry" on:click=move |_| retry.retry()>"Retry"</button>
+                </div>
+            }
+        },
+    );
+    let _ = render_to(html! { <main>{boundary}</main> }, &test_div());
+
+    let click = |id: &str| {
+        document()
+            .get_element_by_id(id)
+            .unwrap()
+            .unchecked_into::<HtmlElement>()
+            .click()
+    };
+    let main = document().query_selector("main").unwrap().unwrap();
+    assert_eq!(main.text_content().unwrap(), "ContentFail");
+
+    click("fail");
+    assert_eq!(main.text_content().unwrap(), "could not acquire dataRetry");
+
+    click("retry");
+    assert_eq!(main.text_content().unwrap(), "ContentFail");
+}
+
+#[wasm_bindgen_test]
+fn root_lifecycle_hooks_run_with_render_to() {
+    let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mounted = log.clone();
+    let cleaned = log.clone();
+    on_mount(move || {
+        let inserted = document().query_selector("#root-hooks").unwrap().is_some();
+        mounted.borrow_mut().push(format!("mount {inserted}"));
+    });
+    on_cleanup(move || cleaned.borrow_mut().push("cleanup".to_owned()));
+    let node = html! { <p id="root-hooks">"Root"</p> };
+    assert!(log.borrow().is_empty());
+
+    let mut rendered = render_to(node, &test_div()).unwrap();
+    assert_eq!(*log.borrow(), ["mount true"]);
+
+    rendered.discard();
+    assert_eq!(*log.borrow(), ["mount true", "cleanup"]);
+}
+
+#[wasm_bindgen_test]
+fn render_to_returns_build_errors() {
+    use hirola_core::render::Error;
+
+    struct Broken;
+
+    impl Render<Dom> for Broken {
+        fn render_into(self: Box<Self>, _parent: &Dom) -> Result<(), Error> {
+            Err(Error::Missing("data"))
+        }
+    }
+
+    let node = html! { <main><p>{Broken}</p></main> };
+
+    assert!(matches!(
+        render_to(node, &test_div()),
+        Err(Error::Missing("data"))
+    ));
+}
+
+#[wasm_bindgen_test]
+fn portal_content_removed_with_switch() {
+    let target = Dom::element("aside");
+    let open = Mutable::new(true);
+    let modal = target.clone();
+    let node = html! {
+        <main>
+            {if open.signal() as Signal {
+                html! { <div>{Portal::new(modal.clone(), html! { <dialog>"Saved"</dialog> })}</div> }
+            } else {
+                html! { <p>"Closed"</p> }
+            }}
+        </main>
+    };
+    let _ = render_to(html! { <div>{target}{node}</div> }, &test_div());
+
+    let aside = document().query_selector("aside").unwrap().unwrap();
+    next_tick_with(&aside, |aside| {
+        assert_eq!(aside.text_content().unwrap(), "Saved");
+    });
+
+    open.set(false);
+    next_tick_with(&aside, |aside| {
+        assert_eq!(aside.inner_html(), "");
+    });
+}
+
+#[wasm_bindgen_test]
+fn children_follow_insertions_and_replacements() {
+    let list = Dom::element("ul");
+    let [a, b, c, d] = ["a", "b", "c", "d"].map(|text| {
+        let item = Dom::element("li");
+        item.append_child(&Dom::text_node(text)).unwrap();
+        item
+    });
+    list.append_child(&a).unwrap();
+    list.append_child(&c).unwrap();
+    list.insert_child_before(&b, Some(&c)).unwrap();
+    list.replace_child(&c, &d).unwrap();
+    list.insert_child_before(&a, None).unwrap();
+
+    let clone = list.clone();
+    clone.remove_child(&b).unwrap();
+    assert_eq!(list.children().take(), vec![d.clone(), a.clone()]);
+    assert_eq!(
+        list.clone()
+            .unchecked_into::<web_sys::Element>()
+            .inner_html(),
+        "<li>d</li><li>a</li>"
+    );
+}
+
+#[wasm_bindgen_test]
+fn removed_content_stops_updating() {
+    let show = Mutable::new(true);
+    let count = Mutable::new(0);
+    let p_ref = NodeRef::new();
+    let shown = count.clone();
+    let p = p_ref.clone();
+    let node = html! {
+        <div>
+            {if show.signal() as Signal {
+                html! { <p bind:ref=p.clone()>{shown.clone()}</p> }
+            } else {
+                html! { <span>"Hidden"</span> }
+            }}
+        </div>
+    };
+    let _ = render_to(node, &test_div());
+
+    next_tick(move || {
+        let p = p_ref.get().unchecked_into::<HtmlElement>();
+        show.set(false);
+        count.set(1);
+        next_tick_with(&p, |p| {
+            assert!(!p.is_connected());
+            assert_eq!(p.text_content().unwrap(), "0");
+        });
+    });
+}
+
+#[wasm_bindgen_test]
+fn batch_applies_updates_before_returning() {
+    set_schedule(Schedule::Microtask);
+    let first = Mutable::new("Jane");
+    let last = Mutable::new("Doe");
+    let node = html! { <p>{first.clone()}" "{last.clone()}</p> };
+    let _ = render_to(node, &test_div());
+
+    let p = document().query_selector("p").unwrap().unwrap();
+    batch(|| {
+        first.set("John");
+        last.set("Smith");
+        assert_eq!(p.text_content().unwrap(), "Jane Doe");
+    });
+    assert_eq!(p.text_content().unwrap(), "John Smith");
+}
+
+#[wasm_bindgen_test]
+fn async_handlers_spawn_on_events() {
+    let saved = Mutable::new(0);
+    let gate = Mutable::new(false);
+    let (count, open) = (saved.clone(), gate.clone());
+    let slow = AsyncCallback::new(async move |_| {
+        open.signal().wait_for(true).await;
+        count.replace_with(|count| *count + 1);
+    })
+    .ignore_overlapping();
+    let count = saved.clone();
+    let node = html! {
+        <div>
+            <button id="save" on:click=async move |_| count.set(10)>"Save"</button>
+            <button id="slow" on:click=slow>"Slow"</button>
+        </div>
+    };
+    let _ = render_to(node, &test_div());
+
+    let click = |id: &str| {
+        document()
+            .get_element_by_id(id)
+            .unwrap()
+            .unchecked_into::<HtmlElement>()
+            .click()
+    };
+    click("save");
+    click("slow");
+    click("slow");
+    gate.set(true);
+    next_tick(move || assert_eq!(saved.get(), 11));
+}
+
+struct Card;
+
+impl Styled for Card {
+    fn style() -> Style {
+        style! {
+            ".card" {
+                "padding": "4px";
+            }
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn styled_rules_are_inserted_into_a_stylesheet() {
+    let node = Card::styled(html! { <div class=Card::class("card")>"Card"</div> });
+    let _ = render_to(node, &test_div());
+
+    let sheet = document()
+        .query_selector("head > style")
+        .unwrap()
+        .unwrap()
+        .unchecked_into::<web_sys::HtmlStyleElement>()
+        .sheet()
+        .unwrap()
+        .unchecked_into::<web_sys::CssStyleSheet>();
+    assert!(sheet.css_rules().unwrap().length() > 0);
+    let card = document()
+        .query_selector("div#test-container > div")
+        .unwrap();
+    assert_eq!(
+        card.unwrap().get_attribute("class").unwrap(),
+        Card::class("card")
+    );
+}
+
+#[wasm_bindgen_test]
+fn class_and_style_directives_update_single_entries() {
+    let active = Mutable::new(false);
+    let node = html! {
+        <p class="card" class:is-active=active.signal() style:color="red">"Card"</p>
+    };
+    let _ = render_to(node, &test_div());
+
+    let p = document().query_selector("p").unwrap().unwrap();
+    p.class_list().add_1("external").unwrap();
+    assert_eq!(p.get_attribute("style").unwrap(), "color: red;");
+
+    active.set(true);
+    next_tick_with(&p, |p| {
+        assert_eq!(p.class_name(), "card external is-active");
+    });
+}
+
+#[wasm_bindgen_test]
+fn prop_directive_sets_properties() {
+    let value = Mutable::new(String::from("Draft"));
+    let node = html! {
+        <input prop:value=value.clone() prop:indeterminate=true/>
+    };
+    let _ = render_to(node, &test_div());
+
+    let input = document()
+        .query_selector("input")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .unwrap();
+    assert_eq!(input.value(), "Draft");
+    assert!(input.indeterminate());
+    assert_eq!(input.get_attribute("value"), None);
+
+    // Setting the same value resets what the user typed
+    input.set_value("Edited");
+    value.set(String::from("Draft"));
+    next_tick_with(&input, |input| {
+        assert_eq!(input.value(), "Draft");
+    });
+}
+
+#[wasm_bindgen_test]
+fn bind_checked_and_group() {
+    let agreed = Mutable::new(false);
+    let size = Mutable::new(String::from("m"));
+    let toppings = MutableVec::new_with_values(vec![String::from("ham")]);
+    let node = html! {
+        <form>
+            <input id="agreed" type="checkbox" bind:checked=&agreed/>
+            <input id="s" type="radio" name="size" value="s" bind:group=&size/>
+            <input id="m" type="radio" name="size" value="m" bind:group=&size/>
+            <input id="ham" type="checkbox" value="ham" bind:group=&toppings/>
+            <input id="olives" type="checkbox" value="olives" bind:group=&toppings/>
+        </form>
+    };
+    let _ = render_to(node, &test_div());
+
+    let input = |id: &str| {
+        document()
+            .get_element_by_id(id)
+            .unwrap()
+            .dyn_into::<web_sys::HtmlInputElement>()
+            .unwrap()
+    };
+    next_tick(move || {
+        assert!(input("m").checked());
+        assert!(!input("s").checked());
+        assert!(input("ham").checked());
+
+        input("agreed").click();
+        input("s").click();
+        input("olives").click();
+        input("ham").click();
+        assert!(agreed.get());
+        assert_eq!(size.get_cloned(), "s");
+        assert_eq!(*toppings.lock_ref(), ["olives"]);
+    });
+}
+
+#[wasm_bindgen_test]
+fn bind_value_on_select_and_textarea() {
+    let color = Mutable::new(String::from("blue"));
+    let colors = MutableVec::new_with_values(vec![String::from("red")]);
+    let notes = Mutable::new(String::from("Hello"));
+    let node = html! {
+        <form>
+            <select id="color" bind:value=&color>
+                <option value="red">"Red"</option>
+                <option value="blue">"Blue"</option>
+            </select>
+            <select id="colors" multiple=true bind:value=&colors>
+                <option value="red">"Red"</option>
+                <option value="blue">"Blue"</option>
+            </select>
+            <textarea bind:value=&notes></textarea>
+        </form>
+    };
+    let _ = render_to(node, &test_div());
+
+    next_tick(move || {
+        let select = |id: &str| {
+            document()
+                .get_element_by_id(id)
+                .unwrap()
+                .dyn_into::<web_sys::HtmlSelectElement>()
+                .unwrap()
+        };
+        let textarea = document().query_selector("textarea").unwrap().unwrap();
+        let value = js_sys::Reflect::get(&textarea, &"value".into()).unwrap();
+        assert_eq!(value.as_string().unwrap(), "Hello");
+        assert_eq!(select("color").value(), "blue");
+        assert_eq!(select("colors").selected_index(), 0);
+
+        select("color").set_value("red");
+        select("color")
+            .dispatch_event(&web_sys::Event::new("input").unwrap())
+            .unwrap();
+        assert_eq!(color.get_cloned(), "red");
+
+        select("colors").set_selected_index(1);
+        select("colors")
+            .dispatch_event(&web_sys::Event::new("input").unwrap())
+            .unwrap();
+        assert_eq!(*colors.lock_ref(), ["blue"]);
+    });
+}
+
+#[wasm_bindgen_test]
+fn bind_value_keeps_invalid_text() {
+    let count = Mutable::new(1u32);
+    let age = ValueBinding::new(&Mutable::new(18u32));
+    let limit = ValueBinding::optional(&Mutable::new(Some(5u32)));
+    let node = html! {
+        <form>
+            <input id="count" bind:value=&count/>
+            <input id="age" bind:value=&age/>
+            <input id="limit" bind:value=&limit/>
+        </form>
+    };
+    let _ = render_to(node, &test_div());
+
+    let input = |id: &str| {
+        document()
+            .get_element_by_id(id)
+            .unwrap()
+            .dyn_into::<web_sys::HtmlInputElement>()
+            .unwrap()
+    };
+    let type_text = move |id: &str, text: &str| {
+        input(id).set_value(text);
+        input(id)
+            .dispatch_event(&web_sys::Event::new("input").unwrap())
+            .unwrap();
+    };
+    next_tick(move || {
+        type_text("count", "1x");
+        type_text("age", "");
+        type_text("limit", "none");
+        assert_eq!(count.get(), 1);
+        assert_eq!(age.value().get(), 18);
+        assert!(age.get_error().is_some());
+        assert_eq!(limit.value().get(), None);
+
+        let age = age.clone();
+        next_tick(move || {
+            assert_eq!(input("count").value(), "1x");
+            assert_eq!(input("age").value(), "");
+            assert_eq!(input("limit").value(), "none");
+
+            type_text("age", "21");
+            assert_eq!(age.value().get(), 21);
+            assert_eq!(age.get_error(), None);
+        });
+    });
+}
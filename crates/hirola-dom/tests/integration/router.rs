@@ -58,13 +58,17 @@ fn test_router_insert_and_render() {
     let rendered = router.clone().render(&app, body);
 
     next_tick(move || {
-        assert_eq!(rendered.inner_html(), home_dom.inner_html());
+        assert_eq!(
+            rendered.inner_html().unwrap(),
+            home_dom.inner_html().unwrap()
+        );
         router.push("/about");
 
         let about_dom = (router.handler().at("/about").unwrap().value)(&app);
-        assert_eq!(rendered.inner_html(), about_dom.inner_html());
-        
-        
+        assert_eq!(
+            rendered.inner_html().unwrap(),
+            about_dom.inner_html().unwrap()
+        );
     })
 }
 
@@ -89,15 +93,15 @@ fn test_router_push_and_render() {
     next_tick(move || {
         let about_dom = (router.handler().at("/about").unwrap().value)(&app);
         assert_eq!(
-            router.clone().render(&app, &body).inner_html(),
-            about_dom.inner_html()
+            router.clone().render(&app, &body).inner_html().unwrap(),
+            about_dom.inner_html().unwrap()
         );
         router.push("/");
         let home_dom = (router.handler().at("/").unwrap().value)(&app);
 
         assert_eq!(
-            router.render(&app, &body).inner_html(),
-            home_dom.inner_html()
+            router.render(&app, &body).inner_html().unwrap(),
+            home_dom.inner_html().unwrap()
         );
     });
 }
@@ -110,8 +114,8 @@ fn test_router_not_found() {
     let not_found_dom = not_found_page(&app);
     next_tick(move || {
         assert_eq!(
-            router.render(&app, &body()).inner_html(),
-            not_found_dom.inner_html()
+            router.render(&app, &body()).inner_html().unwrap(),
+            not_found_dom.inner_html().unwrap()
         );
     });
 }
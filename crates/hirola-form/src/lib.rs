@@ -80,9 +80,12 @@ where
             });
             dom.event("input", handler);
             let value: String = form.get_value_by_field(&column).unwrap_or_default();
-            dom.set_attribute("value", &value);
-
-            dom.set_attribute("name", &column.name());
+            if let Err(error) = dom
+                .set_attribute("value", &value)
+                .and_then(|_| dom.set_attribute("name", &column.name()))
+            {
+                dom.report_error(error);
+            }
         };
 
         Box::new(cb)
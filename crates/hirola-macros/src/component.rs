@@ -59,9 +59,7 @@ pub fn create_function_component(f: syn::ItemFn) -> TokenStream {
                     #inputs_reading
                     #block
                 };
-                hirola::prelude::GenericNode::append_child(dom, &result);
-                // Box::new(result).render_into(&dom)?;
-                Ok(())
+                hirola::prelude::GenericNode::append_child(dom, &result)
             }
         }
     })
@@ -7,8 +7,8 @@ use rstml::{
     Parser, ParserConfig,
 };
 use syn::{
-    parse_macro_input, spanned::Spanned, Block, Data, DeriveInput, Expr, ExprCast, ExprForLoop,
-    ExprIf, ExprMatch, Fields, ItemFn, Stmt, Type,
+    parse_macro_input, parse_quote, spanned::Spanned, Arm, Block, Data, DeriveInput, Expr,
+    ExprCast, ExprForLoop, ExprIf, ExprMatch, Fields, ItemFn, Pat, PatType, Stmt, Type,
 };
 
 mod component;
@@ -34,11 +34,11 @@ fn to_token_stream(input: proc_macro::TokenStream) -> TokenStream {
 
 fn fragment_to_tokens(nodes: Vec<Node>) -> TokenStream {
     let mut tokens = TokenStream::new();
-    let children_tokens = children_to_tokens(nodes);
+    let build = build_template(children_to_tokens(nodes));
     tokens.extend(quote! {
             {
-                let mut template =  ::hirola::prelude::GenericNode::fragment();
-                #children_tokens
+                let template = ::hirola::prelude::GenericNode::fragment();
+                #build
                 template
             }
     });
@@ -54,12 +54,12 @@ fn node_to_tokens(node: Node) -> TokenStream {
             if name[0..1].to_lowercase() == name[0..1] {
                 let attributes = node.attributes().iter().map(attribute_to_tokens);
 
-                let children_tokens = children_to_tokens(node.children.clone());
+                let build = build_template(children_to_tokens(node.children.clone()));
 
                 tokens.extend(quote! {
                     {
                         let template = ::hirola::prelude::GenericNode::element(#name);
-                        #children_tokens
+                        #build
                         #(#attributes)*
                         template
                      }
@@ -86,7 +86,7 @@ fn node_to_tokens(node: Node) -> TokenStream {
                     })
                     .collect::<Vec<TokenStream>>();
                 if !node.children.is_empty() {
-                    let children_tokens = children_to_tokens(node.children);
+                    let children_tokens = fragment_to_tokens(node.children);
                     attributes.extend(vec![quote! {
                         children: {
                             Box::new(#children_tokens)
@@ -121,10 +121,57 @@ fn attribute_to_tokens(attribute: &NodeAttribute) -> TokenStream {
             let value = attr.value();
             let parts: Vec<&str> = name.split(':').collect();
             if parts.len() == 2 {
+                // Class names and style properties are not identifiers, eg. `class:is-active`, and
+                // property names are case sensitive, eg. `prop:selectedIndex`
+                match parts[0] {
+                    "class" => {
+                        let class = parts[1];
+                        return quote! {
+                            ::hirola::prelude::SideEffect::effect(&::hirola::prelude::ClassEffect, &template, ::hirola::prelude::ClassName(#class), #value);
+                        };
+                    }
+                    "style" => {
+                        let property = parts[1];
+                        return quote! {
+                            ::hirola::prelude::SideEffect::effect(&::hirola::prelude::StyleEffect, &template, ::hirola::prelude::StyleProperty(#property), #value);
+                        };
+                    }
+                    // Implemented by the backend, eg. `hirola::dom` sets the property of the element
+                    "prop" => {
+                        let property = parts[1];
+                        return quote! {
+                            ::hirola::prelude::SideEffect::effect(&::hirola::prelude::PropEffect, &template, ::hirola::prelude::Property(#property), #value);
+                        };
+                    }
+                    _ => {}
+                }
                 let name_space =
                     format_ident!("{}Effect", &parts[0].to_pascal_case());
                 let attr = &parts[1].to_pascal_case();
                 let attr_space = format_ident!("{}", attr);
+                // `on:click=async move |e| ..` spawns the returned future on every event
+                if let (["on", _], Some(Expr::Closure(closure))) = (parts.as_slice(), value) {
+                    if closure.asyncness.is_some() {
+                        return quote! {
+                            ::hirola::prelude::SideEffect::effect(&#name_space, &template, #attr_space, ::hirola::prelude::AsyncCallback::new(#value));
+                        };
+                    }
+                    // Handlers can be async too, so the event type is not inferred for closures
+                    let mut closure = closure.clone();
+                    for input in closure.inputs.iter_mut() {
+                        if !matches!(input, Pat::Type(_)) {
+                            *input = Pat::Type(PatType {
+                                attrs: Vec::new(),
+                                pat: Box::new(input.clone()),
+                                colon_token: Default::default(),
+                                ty: Box::new(parse_quote!(::hirola::dom::Event)),
+                            });
+                        }
+                    }
+                    return quote! {
+                        ::hirola::prelude::SideEffect::effect(&#name_space, &template, #attr_space, #closure);
+                    };
+                }
                 quote! {
                     ::hirola::prelude::SideEffect::effect(&#name_space, &template, #attr_space, #value);
                 }
@@ -140,6 +187,39 @@ fn attribute_to_tokens(attribute: &NodeAttribute) -> TokenStream {
     }
 }
 
+/// The result of building `template`, named so that the blocks of the template cannot see it.
+fn build_result() -> Ident {
+    Ident::new("result", Span::mixed_site())
+}
+
+/// Builds `template` with the statements of `steps`, see [`build_step`]. The first error raised
+/// is kept on `template`, to be returned once it is rendered.
+fn build_template(steps: TokenStream) -> TokenStream {
+    let result = build_result();
+    quote! {
+        let mut #result: ::std::result::Result<(), ::hirola::prelude::Error> =
+            ::std::result::Result::Ok(());
+        #steps
+        if let ::std::result::Result::Err(error) = #result {
+            ::hirola::prelude::GenericNode::keep_error(&template, error);
+        }
+    }
+}
+
+/// A statement of [`build_template`] running `step`, which returns a `Result`.
+fn build_step(step: TokenStream) -> TokenStream {
+    let result = build_result();
+    quote! {
+        {
+            #[allow(unused_braces)]
+            let step = #step;
+            if #result.is_ok() {
+                #result = step;
+            }
+        }
+    }
+}
+
 fn children_to_tokens(children: Vec<Node>) -> TokenStream {
     let mut append_children = Vec::new();
     let mut tokens = TokenStream::new();
@@ -153,37 +233,33 @@ fn children_to_tokens(children: Vec<Node>) -> TokenStream {
                     match child {
                         // Its a component
                         Node::Element(_) if name[0..1].to_lowercase() != name[0..1] => {
-                            append_children.extend(quote! {
-                                #[allow(unused_braces)]
-                                ::hirola::prelude::GenericNode::append_render(&template, #node );
-                            });
+                            append_children.push(build_step(quote! {
+                                ::hirola::prelude::GenericNode::append_render(&template, #node)
+                            }));
                         }
                         _ => {
-                            append_children.extend(quote! {
-                                #[allow(unused_braces)]
-                                ::hirola::prelude::GenericNode::append_child(&template, &#node );
-                            });
+                            append_children.push(build_step(quote! {
+                                ::hirola::prelude::GenericNode::append_built(&template, &#node)
+                            }));
                         }
                     }
                 }
                 Node::Text(text) => {
-                    append_children.extend(quote! {
+                    append_children.push(build_step(quote! {
                         ::hirola::prelude::GenericNode::append_child(
                             &template,
-                            #[allow(unused_braces)]
                             &::hirola::prelude::GenericNode::text_node(#text),
-                        );
-                    });
+                        )
+                    }));
                 }
                 Node::Comment(comment) => {
                     let s = comment.value.clone();
-                    append_children.extend(quote! {
+                    append_children.push(build_step(quote! {
                         ::hirola::prelude::GenericNode::append_child(
                             &template,
-                            #[allow(unused_braces)]
                             &::hirola::prelude::GenericNode::comment(#s),
-                        );
-                    });
+                        )
+                    }));
                 }
                 Node::Doctype(_) => {}
                 Node::Block(block) => match block {
@@ -194,40 +270,38 @@ fn children_to_tokens(children: Vec<Node>) -> TokenStream {
                             if let Expr::Cast(ExprCast { ty, expr, .. }) = expr.as_ref() {
                                 match ty.as_ref() {
                                     &Type::Infer(_) => {
-                                        append_children.extend(quote! {
-                                            let template = {
-                                                let props = ::hirola::prelude::IndexedProps {
-                                                    iterable: #expr,
-                                                    template: move | #pat | {
-                                                        #body
+                                        append_children.push(build_step(quote! {
+                                            ::hirola::prelude::GenericNode::append_render(
+                                                &template,
+                                                ::hirola::prelude::Indexed {
+                                                    props: ::hirola::prelude::IndexedProps {
+                                                        iterable: #expr,
+                                                        template: move | #pat | {
+                                                            #body
+                                                        }
                                                     }
-                                                };
-                                                let indexed = ::hirola::prelude::Indexed {
-                                                    props
-                                                };
-                                                Box::new(indexed)
-                                            };
-                                        });
+                                                },
+                                            )
+                                        }));
                                     }
                                     Type::Path(path) => {
                                         let ident = Ident::new("SignalVec", Span::call_site());
                                         if path.path.is_ident(&ident) {
-                                            append_children.extend(quote! {
-                                                let template = {
-                                                    let props = ::hirola::prelude::IndexedProps {
-                                                        iterable: #expr,
-                                                        template: move | #pat | {
-                                                            #body
+                                            append_children.push(build_step(quote! {
+                                                ::hirola::prelude::GenericNode::append_render(
+                                                    &template,
+                                                    ::hirola::prelude::Indexed {
+                                                        props: ::hirola::prelude::IndexedProps {
+                                                            iterable: #expr,
+                                                            template: move | #pat | {
+                                                                #body
+                                                            }
                                                         }
-                                                    };
-                                                    let indexed = ::hirola::prelude::Indexed {
-                                                        props
-                                                    };
-                                                    Box::new(indexed)
-                                                };
-                                            });
+                                                    },
+                                                )
+                                            }));
                                         } else {
-                                            append_children.extend(
+                                            append_children.push(
                                                 syn::Error::new(
                                                     ty.span(),
                                                     "expected SignalVec or _",
@@ -237,20 +311,19 @@ fn children_to_tokens(children: Vec<Node>) -> TokenStream {
                                         }
                                     }
                                     _ => {
-                                        append_children.extend(
+                                        append_children.push(
                                             syn::Error::new(ty.span(), "expected SignalVec or _")
                                                 .to_compile_error(),
                                         );
                                     }
                                 }
                             } else {
-                                append_children.extend(quote! {
+                                let step = build_step(quote! {
+                                    ::hirola::prelude::GenericNode::append_built(&template, &#body)
+                                });
+                                append_children.push(quote! {
                                     for #pat in #expr {
-                                        ::hirola::prelude::GenericNode::append_child(
-                                            &template,
-                                            #[allow(unused_braces)]
-                                            &#body,
-                                        );
+                                        #step
                                     }
                                 });
                             }
@@ -264,61 +337,39 @@ fn children_to_tokens(children: Vec<Node>) -> TokenStream {
                             let (_, else_branch) = else_branch.unwrap();
 
                             if let Expr::Cast(ExprCast { ty, expr, .. }) = cond.as_ref() {
+                                let switch = quote! {
+                                    ::hirola::prelude::Switch {
+                                        signal: #expr,
+                                        renderer: move |res| {
+                                            if res {
+                                                #then_branch
+                                            } else {
+                                                #else_branch
+                                            }
+                                        }
+                                    }
+                                };
                                 match ty.as_ref() {
                                     &Type::Infer(_) => {
-                                        append_children.extend(quote! {
-                                            #[allow(unused_braces)]
-                                            let switch = {
-                                                let switch = ::hirola::prelude::Switch {
-                                                    signal: #expr,
-                                                    renderer: move |res| {
-                                                        if res {
-                                                            #then_branch
-                                                        } else {
-                                                            #else_branch
-                                                        }
-                                                    }
-                                                };
-                                                switch
-                                            };
-                                            ::hirola::prelude::GenericNode::append_render(
-                                                &template,
-                                                switch
-                                            );
-                                        });
+                                        append_children.push(build_step(quote! {
+                                            ::hirola::prelude::GenericNode::append_render(&template, #switch)
+                                        }));
                                     }
                                     Type::Path(path) => {
                                         let ident = Ident::new("Signal", Span::call_site());
                                         if path.path.is_ident(&ident) {
-                                            append_children.extend(quote! {
-                                                let switch = {
-                                                    #[allow(unused_braces)]
-                                                    let switch = ::hirola::prelude::Switch {
-                                                        signal: #expr,
-                                                        renderer: move |res| {
-                                                            if res {
-                                                                #then_branch
-                                                            } else {
-                                                                #else_branch
-                                                            }
-                                                        }
-                                                    };
-                                                    switch
-                                                };
-                                                ::hirola::prelude::GenericNode::append_render(
-                                                    &template,
-                                                    switch
-                                                );
-                                            });
+                                            append_children.push(build_step(quote! {
+                                                ::hirola::prelude::GenericNode::append_render(&template, #switch)
+                                            }));
                                         } else {
-                                            append_children.extend(
+                                            append_children.push(
                                                 syn::Error::new(ty.span(), "expected Signal or _")
                                                     .to_compile_error(),
                                             );
                                         }
                                     }
                                     _ => {
-                                        append_children.extend(
+                                        append_children.push(
                                             syn::Error::new(
                                                 ty.span(),
                                                 "expected Signal, SignalVec or _",
@@ -328,57 +379,73 @@ fn children_to_tokens(children: Vec<Node>) -> TokenStream {
                                     }
                                 }
                             } else {
-                                append_children.extend(quote! {
-                                    ::hirola::prelude::GenericNode::append_child(
-                                        &template,
-                                        #[allow(unused_braces)]
-                                        &#block,
-                                    );
-                                });
+                                append_children.push(build_step(quote! {
+                                    ::hirola::prelude::GenericNode::append_built(&template, &#block)
+                                }));
                             }
                         }
 
                         Some(Control::Match(ExprMatch { expr, arms, .. })) => match *expr {
                             Expr::Await(fut) => {
                                 let fut = fut.base;
-                                append_children.extend(quote! {
-                                    let suspense = {
-                                        #[allow(unused_braces)]
-                                        let suspense = ::hirola::prelude::Suspense {
-                                            future: Box::pin(#fut),
-                                            template: Box::new(move |res| {
+                                append_children.push(build_step(quote! {
+                                    ::hirola::prelude::GenericNode::append_render(
+                                        &template,
+                                        ::hirola::prelude::Suspense::new(
+                                            #fut,
+                                            move |res| {
                                                 match res {
                                                     #(#arms)*
                                                 }
-                                            })
-                                        };
-                                        suspense
-                                    };
+                                            },
+                                        )
+                                        .with_default_fallback(),
+                                    )
+                                }));
+                            }
+                            Expr::Cast(ExprCast { ty, expr, .. })
+                                if matches!(ty.as_ref(), Type::Infer(_))
+                                    || matches!(ty.as_ref(), Type::Path(path) if path.path.is_ident("Signal")) =>
+                            {
+                                // Each value is matched once, the index of the arm keys the view
+                                let arms = arms.iter().enumerate().map(|(index, arm)| {
+                                    let Arm { attrs, pat, guard, body, .. } = arm;
+                                    let guard = guard
+                                        .as_ref()
+                                        .map(|(if_token, guard)| quote! { #if_token #guard });
+                                    quote! { #(#attrs)* #pat #guard => (#index, #body), }
+                                });
+                                append_children.push(build_step(quote! {
                                     ::hirola::prelude::GenericNode::append_render(
                                         &template,
-                                        suspense
-                                    );
-
-                                });
+                                        ::hirola::prelude::MatchArms::new(
+                                            #expr,
+                                            move |value| match value {
+                                                #(#arms)*
+                                            },
+                                        ),
+                                    )
+                                }));
+                            }
+                            Expr::Cast(ExprCast { ty, .. }) => {
+                                append_children.push(
+                                    syn::Error::new(ty.span(), "expected Signal or _")
+                                        .to_compile_error(),
+                                );
                             }
                             _ => {
-                                append_children.extend(quote! {
-                                    ::hirola::prelude::GenericNode::append_child(
-                                        &template,
-                                        #[allow(unused_braces)]
-                                        &#block,
-                                    );
-                                });
+                                append_children.push(build_step(quote! {
+                                    ::hirola::prelude::GenericNode::append_built(&template, &#block)
+                                }));
                             }
                         },
                         _ => {
-                            append_children.extend(quote! {
-                                let _ = ::hirola::prelude::Render::render_into(
-                                    #[allow(unused_braces)]
+                            append_children.push(build_step(quote! {
+                                ::hirola::prelude::Render::render_into(
                                     Box::new(#block),
                                     &template,
-                                );
-                            });
+                                )
+                            }));
                         }
                     },
                     NodeBlock::Invalid { body, .. } => {
@@ -436,6 +503,31 @@ fn convert_name(name: &str) -> String {
     out
 }
 
+/// Builds a node from a template of elements, text and `{}` blocks.
+///
+/// A block renders any value that implements `Render`: text, numbers, nodes, `Option`s and
+/// `Vec`s of them, and signals of any of these, which update the content in place.
+///
+/// A `Mutable` of a type that only implements `Display`, eg. an enum of your own, is rendered as
+/// text as it is. Its signals are not, since their items have to implement `Render`: map them to
+/// `DisplayText`.
+///
+/// ```rust,ignore
+/// let status = Mutable::new(Status::Online);
+/// html! {
+///     <p>{status.clone()}</p>
+///     <p>{status.signal_cloned().map(DisplayText)}</p>
+/// }
+/// ```
+///
+/// Attributes take an `AttributeValue`, or a signal of one. Strings and numbers are set as they
+/// are, `bool` adds or removes the attribute and `Option` removes it when `None`. Other `Display`
+/// types are no longer set through their `Display` output as they are: wrap them in `Displayed`.
+///
+/// ```rust,ignore
+/// let theme = Mutable::new(Theme::Dark);
+/// html! { <main data-theme=theme.signal_cloned().map(Displayed)></main> }
+/// ```
 #[proc_macro]
 pub fn html(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let output = to_token_stream(input);
@@ -591,3 +683,160 @@ pub fn fields_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     gen.into()
 }
+
+/// Whether `ty` is a `Vec<T>`, returning `T`.
+fn vec_item(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(item) => Some(item),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Generates `{Name}Store`, a reactive mirror of a struct with named fields.
+///
+/// Every field becomes a `Mutable`, or a `MutableVec` for `Vec` fields, with a `{field}_signal`
+/// (or `{field}_signal_vec`) method. `snapshot()` reads the store back into the plain struct and
+/// `replace(plain)` updates every field. The store implements `Callback`, so it can be used with
+/// `on:` handlers directly.
+///
+/// ```rust,ignore
+/// #[derive(Clone, Store)]
+/// struct Todo {
+///     title: String,
+///     tags: Vec<String>,
+/// }
+///
+/// let todo = TodoStore::new(Todo { title: "Write docs".into(), tags: vec![] });
+/// html! { <button on:click=todo.callback(|todo| todo.title.set("Done".into()))>{todo.title.clone()}</button> }
+/// ```
+#[proc_macro_derive(Store)]
+pub fn store(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let store_name = format_ident!("{name}Store");
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = &ast.generics;
+    let mut callback_generics = ast.generics.clone();
+    callback_generics.params.push(syn::parse_quote!(__Event));
+    let (callback_generics, _, _) = callback_generics.split_for_impl();
+
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new(ast.span(), "Store requires a struct with named fields")
+                    .to_compile_error()
+                    .into()
+            }
+        },
+        _ => {
+            return syn::Error::new(ast.span(), "Store can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let mut store_fields = Vec::new();
+    let mut signals = Vec::new();
+    let mut new_fields = Vec::new();
+    let mut snapshot_fields = Vec::new();
+    let mut replace_fields = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let field_vis = &field.vis;
+        let ty = &field.ty;
+        match vec_item(ty) {
+            Some(item) => {
+                let signal = format_ident!("{ident}_signal_vec");
+                store_fields.extend(quote! {
+                    #field_vis #ident: ::hirola::prelude::MutableVec<#item>,
+                });
+                signals.extend(quote! {
+                    #field_vis fn #signal(&self) -> ::hirola::prelude::signal_vec::MutableSignalVec<#item> {
+                        self.#ident.signal_vec_cloned()
+                    }
+                });
+                new_fields.extend(quote! {
+                    #ident: ::hirola::prelude::MutableVec::new_with_values(plain.#ident),
+                });
+                snapshot_fields.extend(quote! {
+                    #ident: self.#ident.lock_ref().to_vec(),
+                });
+                replace_fields.extend(quote! {
+                    self.#ident.lock_mut().replace_cloned(plain.#ident);
+                });
+            }
+            None => {
+                let signal = format_ident!("{ident}_signal");
+                store_fields.extend(quote! {
+                    #field_vis #ident: ::hirola::prelude::Mutable<#ty>,
+                });
+                signals.extend(quote! {
+                    #field_vis fn #signal(&self) -> ::hirola::prelude::signal::MutableSignalCloned<#ty> {
+                        self.#ident.signal_cloned()
+                    }
+                });
+                new_fields.extend(quote! {
+                    #ident: ::hirola::prelude::Mutable::new(plain.#ident),
+                });
+                snapshot_fields.extend(quote! {
+                    #ident: self.#ident.get_cloned(),
+                });
+                replace_fields.extend(quote! {
+                    self.#ident.set(plain.#ident);
+                });
+            }
+        }
+    }
+
+    let gen = quote! {
+        #[derive(Clone)]
+        #vis struct #store_name #generics #where_clause {
+            #(#store_fields)*
+        }
+
+        impl #impl_generics #store_name #ty_generics #where_clause {
+            /// Creates a store holding the fields of `plain`.
+            #vis fn new(plain: #name #ty_generics) -> Self {
+                #store_name {
+                    #(#new_fields)*
+                }
+            }
+
+            /// Reads the current value of every field.
+            #vis fn snapshot(&self) -> #name #ty_generics {
+                #name {
+                    #(#snapshot_fields)*
+                }
+            }
+
+            /// Updates every field with the value it has in `plain`.
+            #vis fn replace(&self, plain: #name #ty_generics) {
+                #(#replace_fields)*
+            }
+
+            #(#signals)*
+        }
+
+        impl #impl_generics From<#name #ty_generics> for #store_name #ty_generics #where_clause {
+            fn from(plain: #name #ty_generics) -> Self {
+                #store_name::new(plain)
+            }
+        }
+
+        impl #callback_generics ::hirola::prelude::Callback<__Event> for #store_name #ty_generics #where_clause {}
+    };
+
+    gen.into()
+}
@@ -0,0 +1,57 @@
+use futures_util::FutureExt;
+use hirola::prelude::*;
+use hirola::signal::SignalExt;
+use hirola_ssr::{render_to_string_async, SsrNode};
+
+#[derive(Debug, Clone, PartialEq, Store)]
+struct Todo {
+    title: String,
+    done: bool,
+    tags: Vec<&'static str>,
+}
+
+fn todo() -> Todo {
+    Todo {
+        title: "Write docs".to_owned(),
+        done: false,
+        tags: vec!["docs"],
+    }
+}
+
+#[test]
+fn store_mirrors_struct() {
+    let store = TodoStore::new(todo());
+    store.done.set(true);
+    store.tags.lock_mut().push("urgent");
+    assert_eq!(
+        store.snapshot(),
+        Todo {
+            done: true,
+            tags: vec!["docs", "urgent"],
+            ..todo()
+        }
+    );
+
+    store.replace(todo());
+    assert_eq!(store.snapshot(), todo());
+}
+
+#[test]
+fn store_renders_field_signals() {
+    let store = TodoStore::from(todo());
+    let toggle = store.callback(|store| store.done.set(!store.done.get()));
+    let node: SsrNode = html! {
+        <li class=store.done_signal().map(|done| if done { "done" } else { "" })>
+            {store.title_signal()}
+            {store.tags_signal_vec().map_render(|tag| html! { <span>{tag}</span> })}
+        </li>
+    };
+    toggle(());
+    assert_eq!(
+        r#"<li class="done"><!---->Write docs<!----><span>docs</span><!----></li>"#,
+        render_to_string_async(node)
+            .now_or_never()
+            .expect("effects should settle")
+            .unwrap()
+    );
+}
@@ -0,0 +1,112 @@
+//! The `prop:` directive for [`SsrNode`].
+//!
+//! There are no JavaScript properties to set on the server. Properties that reflect an attribute,
+//! eg. `value`, `checked` or `className`, are rendered as that attribute so the page starts in the
+//! same state, and the others, eg. `indeterminate` or `srcObject`, are ignored.
+use std::pin::Pin;
+
+use hirola_core::effect::{AttributeValue, SideEffect};
+use hirola_core::generic_node::GenericNode;
+use hirola_core::impl_effect_for_signals;
+use hirola_core::prelude::signal::{Signal, SignalExt};
+use hirola_core::prelude::Mutable;
+
+use crate::SsrNode;
+
+pub use hirola_core::effect::{PropEffect, Property};
+
+/// The attribute reflected by the property `name`.
+fn reflected_attribute(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "className" => "class",
+        "htmlFor" => "for",
+        "readOnly" => "readonly",
+        "tabIndex" => "tabindex",
+        "maxLength" => "maxlength",
+        "minLength" => "minlength",
+        "value" => "value",
+        "checked" => "checked",
+        "selected" => "selected",
+        "disabled" => "disabled",
+        "hidden" => "hidden",
+        "multiple" => "multiple",
+        "required" => "required",
+        "open" => "open",
+        "id" => "id",
+        "title" => "title",
+        "name" => "name",
+        "type" => "type",
+        "placeholder" => "placeholder",
+        "href" => "href",
+        "src" => "src",
+        "alt" => "alt",
+        _ => return None,
+    })
+}
+
+fn set_property(node: &SsrNode, name: &str, value: &impl AttributeValue) {
+    let Some(attribute) = reflected_attribute(name) else {
+        return;
+    };
+    match value.to_attribute() {
+        Some(value) => node.set_attribute(attribute, &value),
+        None => node.remove_attribute(attribute),
+    }
+    .unwrap_or_else(|error| node.report_error(error))
+}
+
+macro_rules! impl_prop_effect {
+    ($($value_type:ty),*) => {
+        $(
+            impl SideEffect<Property, $value_type, SsrNode> for PropEffect {
+                fn effect(&self, node: &SsrNode, attr: Property, effect: $value_type) {
+                    set_property(node, attr.0, &effect)
+                }
+            }
+        )*
+    };
+}
+
+impl_prop_effect!(bool, &str, String, char, f32, f64);
+impl_prop_effect!(usize, isize, i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl<T: AttributeValue> SideEffect<Property, Option<T>, SsrNode> for PropEffect {
+    fn effect(&self, node: &SsrNode, attr: Property, effect: Option<T>) {
+        set_property(node, attr.0, &effect)
+    }
+}
+
+impl<A: AttributeValue + Clone + 'static> SideEffect<Property, Mutable<A>, SsrNode> for PropEffect {
+    fn effect(&self, node: &SsrNode, attr: Property, effect: Mutable<A>) {
+        self.effect(node, attr, effect.signal_cloned())
+    }
+}
+
+fn set_property_signal(
+    node: &SsrNode,
+    name: &'static str,
+    signal: impl Signal<Item: AttributeValue> + 'static,
+) {
+    let dom = node.clone();
+    node.effect(signal.for_each(move |value| {
+        set_property(&dom, name, &value);
+        async {}
+    }));
+}
+
+impl_effect_for_signals!(
+    PropEffect,
+    Property,
+    Signal<Item: AttributeValue>,
+    set_property_signal,
+    SsrNode
+);
+
+/// Sets the attribute with a boxed signal, eg. one returned by [`SignalExt::boxed_local`]
+impl<A: AttributeValue + 'static> SideEffect<Property, Pin<Box<dyn Signal<Item = A>>>, SsrNode>
+    for PropEffect
+{
+    fn effect(&self, node: &SsrNode, attr: Property, effect: Pin<Box<dyn Signal<Item = A>>>) {
+        set_property_signal(node, attr.0, effect)
+    }
+}
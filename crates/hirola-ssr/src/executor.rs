@@ -0,0 +1,141 @@
+//! A minimal single threaded executor that drives the effects registered on an [`SsrNode`] tree.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_util::task::AtomicWaker;
+use hirola_core::BoxedLocal;
+
+use crate::{SsrEffect, SsrNode};
+
+/// Wakes a single task and the executor that owns it.
+struct TaskWaker {
+    woken: AtomicBool,
+    executor: Arc<AtomicWaker>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.executor.wake();
+    }
+}
+
+struct Task {
+    future: BoxedLocal<()>,
+    waker: Arc<TaskWaker>,
+    /// The node the effect was registered on.
+    host: SsrNode,
+    suspense: bool,
+}
+
+/// Runs the effects registered on a tree, picking up new ones as they are added.
+pub(crate) struct Executor {
+    root: SsrNode,
+    tasks: Vec<Task>,
+    waker: Arc<AtomicWaker>,
+}
+
+impl Executor {
+    pub(crate) fn new(root: SsrNode) -> Self {
+        Executor {
+            root,
+            tasks: Vec::new(),
+            waker: Arc::new(AtomicWaker::new()),
+        }
+    }
+
+    /// Moves the effects registered since the last call into the task list.
+    fn spawn_new(&mut self) -> bool {
+        let mut effects = Vec::new();
+        self.root.take_effects(&mut effects);
+        let spawned = !effects.is_empty();
+        for (host, SsrEffect { future, suspense }) in effects {
+            self.tasks.push(Task {
+                future,
+                host,
+                suspense,
+                waker: Arc::new(TaskWaker {
+                    woken: AtomicBool::new(true),
+                    executor: Arc::clone(&self.waker),
+                }),
+            });
+        }
+        spawned
+    }
+
+    /// Polls the woken tasks until none of them can make progress without waiting.
+    ///
+    /// Returns the hosts of the [`GenericNode::suspense`] futures that completed.
+    ///
+    /// [`GenericNode::suspense`]: hirola_core::generic_node::GenericNode::suspense
+    pub(crate) fn run_until_stalled(&mut self, cx: &mut Context<'_>) -> Vec<SsrNode> {
+        self.waker.register(cx.waker());
+        let mut resolved = Vec::new();
+        loop {
+            let spawned = self.spawn_new();
+            let mut progressed = false;
+            self.tasks.retain_mut(|task| {
+                if !task.waker.woken.swap(false, Ordering::Acquire) {
+                    return true;
+                }
+                progressed = true;
+                let waker = Waker::from(Arc::clone(&task.waker));
+                let mut cx = Context::from_waker(&waker);
+                let pending = task.future.as_mut().poll(&mut cx).is_pending();
+                if !pending && task.suspense {
+                    resolved.push(task.host.clone());
+                }
+                pending
+            });
+            if !spawned && !progressed {
+                break;
+            }
+        }
+        resolved
+    }
+
+    /// The hosts of the [`GenericNode::suspense`] futures that are still running.
+    ///
+    /// [`GenericNode::suspense`]: hirola_core::generic_node::GenericNode::suspense
+    pub(crate) fn suspended(&self) -> impl Iterator<Item = &SsrNode> {
+        self.tasks
+            .iter()
+            .filter(|task| task.suspense)
+            .map(|task| &task.host)
+    }
+}
+
+/// Polls every effect registered on the tree until it settles.
+///
+/// The tree is settled once every [`GenericNode::suspense`] future has completed and polling the
+/// remaining effects makes no further progress. Effects that are still pending at that point, like
+/// the ones listening to a signal, are dropped together with the executor.
+///
+/// [`GenericNode::suspense`]: hirola_core::generic_node::GenericNode::suspense
+pub(crate) struct Settle(Executor);
+
+impl Settle {
+    pub(crate) fn new(root: SsrNode) -> Self {
+        Settle(Executor::new(root))
+    }
+}
+
+impl Future for Settle {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.0.run_until_stalled(cx);
+        if self.0.suspended().next().is_some() {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
@@ -1,10 +1,22 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::future::Future;
 use std::rc::{Rc, Weak};
 use std::{fmt, mem};
 
+use futures_util::future;
 use hirola_core::generic_node::GenericNode;
+use hirola_core::owner::Owner;
 use hirola_core::render::{Error, Render};
+use hirola_core::templating::{error_boundary, styled};
+use hirola_core::BoxedLocal;
+
+mod effects;
+mod executor;
+mod stream;
+
+pub use effects::{PropEffect, Property};
+pub use stream::{render_to_stream, SsrStream};
 
 /// Rendering backend for Server Side Rendering, aka. SSR.
 /// Offers interior mutability and is not thread safe.
@@ -21,6 +33,24 @@ struct SsrNodeInner {
     ty: Rc<SsrNodeType>,
     /// No parent if `Weak::upgrade` returns `None`.
     parent: RefCell<Weak<SsrNodeInner>>,
+    /// Effects registered on this node that have not been picked up by an executor yet.
+    effects: Rc<RefCell<Vec<SsrEffect>>>,
+    /// Errors passed to [`GenericNode::report_error`], returned by the `render_to_*` functions.
+    errors: Rc<RefCell<Vec<Error>>>,
+}
+
+/// A future registered through [`GenericNode::effect`] or [`GenericNode::suspense`].
+pub(crate) struct SsrEffect {
+    future: BoxedLocal<()>,
+    suspense: bool,
+}
+
+impl fmt::Debug for SsrEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SsrEffect")
+            .field("suspense", &self.suspense)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -39,9 +69,59 @@ impl SsrNode {
         Self(Rc::new(SsrNodeInner {
             ty: Rc::new(ty),
             parent: RefCell::new(Weak::new()), // no parent
+            effects: Default::default(),
+            errors: Default::default(),
         }))
     }
 
+    /// Moves the effects registered on this node and all its descendants into `effects`,
+    /// together with the node they were registered on.
+    fn take_effects(&self, effects: &mut Vec<(SsrNode, SsrEffect)>) {
+        effects.extend(
+            self.0
+                .effects
+                .borrow_mut()
+                .drain(..)
+                .map(|effect| (self.clone(), effect)),
+        );
+        for child in self.children().take() {
+            child.take_effects(effects);
+        }
+    }
+
+    /// Moves the errors reported on this node and all its descendants into `errors`.
+    fn take_errors(&self, errors: &mut Vec<Error>) {
+        errors.append(&mut self.0.errors.borrow_mut());
+        for child in self.children().take() {
+            child.take_errors(errors);
+        }
+    }
+
+    /// Returns the first error reported anywhere in this tree, if any.
+    fn check_errors(&self) -> Result<(), Error> {
+        let mut errors = Vec::new();
+        self.take_errors(&mut errors);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn tag(&self) -> Option<String> {
+        match self.0.ty.as_ref() {
+            SsrNodeType::Element(e) => Some(e.borrow().name.clone()),
+            _ => None,
+        }
+    }
+
+    fn invalid(&self, operation: &'static str) -> Error {
+        Error::InvalidOperation {
+            operation,
+            tag: self.tag(),
+            source: None,
+        }
+    }
+
     fn set_parent(&self, parent: Weak<SsrNodeInner>) {
         if let Some(old_parent) = self.parent_node() {
             old_parent.try_remove_child(self);
@@ -50,53 +130,100 @@ impl SsrNode {
         *self.0.parent.borrow_mut() = parent;
     }
 
-    #[track_caller]
-    fn unwrap_element(&self) -> &RefCell<Element> {
+    fn as_element(&self) -> Result<&RefCell<Element>, Error> {
         match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => e,
-            _ => panic!("node is not an element"),
+            SsrNodeType::Element(e) => Ok(e),
+            _ => Err(Error::FailedCast {
+                expected: "Element",
+                tag: None,
+            }),
         }
     }
 
-    #[track_caller]
-    fn unwrap_text(&self) -> &RefCell<Text> {
+    fn as_text(&self) -> Result<&RefCell<Text>, Error> {
         match &self.0.ty.as_ref() {
-            SsrNodeType::Text(e) => e,
-            _ => panic!("node is not a text node"),
+            SsrNodeType::Text(e) => Ok(e),
+            _ => Err(Error::FailedCast {
+                expected: "Text",
+                tag: self.tag(),
+            }),
+        }
+    }
+
+    /// Takes the children out of this node, to be put back with [`Self::set_children`].
+    fn take_children(&self, operation: &'static str) -> Result<Vec<SsrNode>, Error> {
+        match self.0.ty.as_ref() {
+            SsrNodeType::Element(e) => Ok(mem::take(&mut e.borrow_mut().children.0)),
+            SsrNodeType::Fragment(f) => Ok(mem::take(&mut f.borrow_mut().0)),
+            _ => Err(self.invalid(operation)),
         }
     }
 
-    // FIXME: recursively visit Fragments and call try_remove_child
-    fn try_remove_child(&self, child: &Self) {
-        let mut children = match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => mem::take(&mut e.borrow_mut().children.0),
-            SsrNodeType::Fragment(f) => mem::take(&mut f.borrow_mut().0),
-            _ => panic!("node type cannot have children"),
+    fn set_children(&self, children: Vec<SsrNode>) {
+        match self.0.ty.as_ref() {
+            SsrNodeType::Element(e) => e.borrow_mut().children.0 = children,
+            SsrNodeType::Fragment(f) => f.borrow_mut().0 = children,
+            _ => {}
+        };
+    }
+
+    /// Removes `child` from this node, looking into child fragments the same way the DOM
+    /// flattens them. Returns whether the child was found.
+    fn try_remove_child(&self, child: &Self) -> bool {
+        let Ok(mut children) = self.take_children("remove_child") else {
+            return false;
         };
 
-        if let Some(index) = children
+        let removed = if let Some(index) = children
             .iter()
             .enumerate()
             .find_map(|(i, c)| (c == child).then_some(i))
         {
             children.remove(index);
+            true
         } else {
             // try remove from child Fragments
-            for c in &children {
-                if let SsrNodeType::Fragment(fragment) = c.0.ty.as_ref() {
-                    for c in &fragment.borrow().0 {
-                        c.try_remove_child(child);
-                    }
-                }
+            children.iter().any(|c| {
+                matches!(c.0.ty.as_ref(), SsrNodeType::Fragment(_)) && c.try_remove_child(child)
+            })
+        };
+
+        self.set_children(children);
+        removed
+    }
+}
+
+/// Splits an inline style into its declarations, on the `;` outside quotes and parentheses, so
+/// that values like `url(data:image/png;base64,..)` are kept whole.
+fn style_declarations(style: &str) -> Vec<String> {
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in style.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => {
+                declarations.push(&style[start..index]);
+                start = index + 1;
             }
+            _ => {}
         }
-
-        match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => e.borrow_mut().children.0 = children,
-            SsrNodeType::Fragment(f) => f.borrow_mut().0 = children,
-            _ => panic!("node type cannot have children"),
-        };
     }
+    declarations.push(&style[start..]);
+    declarations
+        .into_iter()
+        .map(str::trim)
+        .filter(|declaration| !declaration.is_empty())
+        .map(str::to_owned)
+        .collect()
 }
 
 impl GenericNode for SsrNode {
@@ -120,103 +247,118 @@ impl GenericNode for SsrNode {
         SsrNode::new(SsrNodeType::Comment(Default::default()))
     }
 
-    fn set_attribute(&self, name: &str, value: &str) {
-        self.unwrap_element()
+    fn set_attribute(&self, name: &str, value: &str) -> Result<(), Error> {
+        self.as_element()?
             .borrow_mut()
             .attributes
             .insert(name.to_string(), value.to_string());
+        Ok(())
     }
 
-    fn append_child(&self, child: &Self) {
-        child.set_parent(Rc::downgrade(&self.0));
+    fn remove_attribute(&self, name: &str) -> Result<(), Error> {
+        self.as_element()?.borrow_mut().attributes.remove(name);
+        Ok(())
+    }
 
-        match self.0.ty.as_ref() {
-            SsrNodeType::Element(element) => element.borrow_mut().children.0.push(child.clone()),
-            SsrNodeType::Fragment(fragment) => fragment.borrow_mut().0.push(child.clone()),
-            _ => panic!("node type cannot have children"),
+    fn toggle_class(&self, name: &str, on: bool) -> Result<(), Error> {
+        let mut element = self.as_element()?.borrow_mut();
+        let class = element.attributes.remove("class").unwrap_or_default();
+        let mut classes: Vec<&str> = class.split_whitespace().collect();
+        match (on, classes.contains(&name)) {
+            (true, false) => classes.push(name),
+            (false, true) => classes.retain(|class| *class != name),
+            _ => {}
         }
+        if !classes.is_empty() {
+            let class = classes.join(" ");
+            element.attributes.insert("class".to_owned(), class);
+        }
+        Ok(())
     }
 
-    fn insert_child_before(&self, new_node: &Self, reference_node: Option<&Self>) {
-        if let Some(reference_node) = reference_node {
-            debug_assert_eq!(
-                reference_node.parent_node().as_ref(),
-                Some(self),
-                "reference node is not a child of this node"
-            );
+    fn set_style_property(&self, property: &str, value: Option<&str>) -> Result<(), Error> {
+        let mut element = self.as_element()?.borrow_mut();
+        let style = element.attributes.remove("style").unwrap_or_default();
+        let mut declarations = style_declarations(&style);
+        let position = declarations
+            .iter()
+            .position(|declaration| declaration.split(':').next().map(str::trim) == Some(property));
+        match (position, value) {
+            (Some(index), Some(value)) => declarations[index] = format!("{property}: {value}"),
+            (Some(index), None) => drop(declarations.remove(index)),
+            (None, Some(value)) => declarations.push(format!("{property}: {value}")),
+            (None, None) => {}
         }
-
-        new_node.set_parent(Rc::downgrade(&self.0));
-
-        let mut children = match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => mem::take(&mut e.borrow_mut().children.0),
-            SsrNodeType::Fragment(f) => mem::take(&mut f.borrow_mut().0),
-            _ => panic!("node type cannot have children"),
-        };
-
-        match reference_node {
-            None => self.append_child(new_node),
-            Some(reference) => {
-                children.insert(
-                    children
-                        .iter()
-                        .enumerate()
-                        .find_map(|(i, child)| (child == reference).then_some(i))
-                        .expect("couldn't find reference node"),
-                    new_node.clone(),
-                );
-            }
+        if !declarations.is_empty() {
+            let style = declarations.join("; ") + ";";
+            element.attributes.insert("style".to_owned(), style);
         }
+        Ok(())
+    }
 
-        match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => e.borrow_mut().children.0 = children,
-            SsrNodeType::Fragment(f) => f.borrow_mut().0 = children,
-            _ => panic!("node type cannot have children"),
-        };
+    fn append_child(&self, child: &Self) -> Result<(), Error> {
+        self.insert_child_before(child, None)
+            .map_err(|error| Error::InvalidOperation {
+                operation: "append_child",
+                tag: self.tag(),
+                source: Some(Box::new(error)),
+            })
     }
 
-    fn remove_child(&self, child: &Self) {
-        let mut children = match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => mem::take(&mut e.borrow_mut().children.0),
-            SsrNodeType::Fragment(f) => mem::take(&mut f.borrow_mut().0),
-            _ => panic!("node type cannot have children"),
-        };
+    fn insert_child_before(
+        &self,
+        new_node: &Self,
+        reference_node: Option<&Self>,
+    ) -> Result<(), Error> {
+        let children = self.take_children("insert_child_before")?;
+        let found = reference_node.is_none_or(|reference| children.contains(reference));
+        self.set_children(children);
+        if !found {
+            return Err(self.invalid("insert_child_before"));
+        }
 
-        let index = children
-            .iter()
-            .enumerate()
-            .find_map(|(i, c)| (c == child).then_some(i))
-            .expect("couldn't find child");
-        children.remove(index);
+        new_node.set_parent(Rc::downgrade(&self.0));
 
-        match self.0.ty.as_ref() {
-            SsrNodeType::Element(e) => e.borrow_mut().children.0 = children,
-            SsrNodeType::Fragment(f) => f.borrow_mut().0 = children,
-            _ => panic!("node type cannot have children"),
-        };
+        let mut children = self.take_children("insert_child_before")?;
+        let index = reference_node
+            .and_then(|reference| children.iter().position(|child| child == reference))
+            .unwrap_or(children.len());
+        children.insert(index, new_node.clone());
+        self.set_children(children);
+        Ok(())
+    }
+
+    fn remove_child(&self, child: &Self) -> Result<(), Error> {
+        if !self.try_remove_child(child) {
+            return Err(self.invalid("remove_child"));
+        }
+        *child.0.parent.borrow_mut() = Weak::new();
+        Ok(())
     }
 
-    fn replace_child(&self, old: &Self, new: &Self) {
+    fn replace_child(&self, old: &Self, new: &Self) -> Result<(), Error> {
+        let children = self.take_children("replace_child")?;
+        let found = children.contains(old);
+        self.set_children(children);
+        if !found {
+            return Err(self.invalid("replace_child"));
+        }
+
         new.set_parent(Rc::downgrade(&self.0));
 
-        let mut ele = self.unwrap_element().borrow_mut();
-        let children = &mut ele.children.0;
-        let index = children
-            .iter()
-            .enumerate()
-            .find_map(|(i, c)| (c == old).then_some(i))
-            .expect("Couldn't find child");
-        children[index] = new.clone();
+        let mut children = self.take_children("replace_child")?;
+        if let Some(index) = children.iter().position(|c| c == old) {
+            children[index] = new.clone();
+            *old.0.parent.borrow_mut() = Weak::new();
+        }
+        self.set_children(children);
+        Ok(())
     }
 
-    fn insert_sibling_before(&self, child: &Self) {
-        child.set_parent(Rc::downgrade(
-            &self.parent_node().expect("no parent for this node").0,
-        ));
-
+    fn insert_sibling_before(&self, child: &Self) -> Result<(), Error> {
         self.parent_node()
-            .unwrap()
-            .insert_child_before(child, Some(self));
+            .ok_or_else(|| self.invalid("insert_sibling_before"))?
+            .insert_child_before(child, Some(self))
     }
 
     fn parent_node(&self) -> Option<Self> {
@@ -224,26 +366,70 @@ impl GenericNode for SsrNode {
     }
 
     fn next_sibling(&self) -> Option<Self> {
-        unimplemented!()
+        let parent = self.parent_node()?;
+        let siblings = parent.children().take();
+        siblings
+            .iter()
+            .skip_while(|sibling| *sibling != self)
+            .nth(1)
+            .cloned()
     }
 
-    fn remove_self(&self) {
-        unimplemented!()
+    fn remove_self(&self) -> Result<(), Error> {
+        match self.parent_node() {
+            Some(parent) => parent.remove_child(self),
+            None => Ok(()),
+        }
     }
 
     fn children(&self) -> RefCell<Vec<SsrNode>> {
-        unimplemented!()
+        let children = match self.0.ty.as_ref() {
+            SsrNodeType::Element(e) => e.borrow().children.0.clone(),
+            SsrNodeType::Fragment(f) => f.borrow().0.clone(),
+            _ => Vec::new(),
+        };
+        RefCell::new(children)
+    }
+
+    fn update_inner_text(&self, text: &str) -> Result<(), Error> {
+        self.as_text()?.borrow_mut().0 = text.to_string();
+        Ok(())
+    }
+
+    fn replace_children_with(&self, node: &Self) -> Result<(), Error> {
+        for child in self.take_children("replace_children_with")? {
+            *child.0.parent.borrow_mut() = Weak::new();
+        }
+        self.append_child(node)
+    }
+
+    fn report_error(&self, error: Error) {
+        if let Err(error) = error_boundary::catch(error) {
+            self.keep_error(error);
+        }
+    }
+
+    fn keep_error(&self, error: Error) {
+        self.0.errors.borrow_mut().push(error);
     }
 
-    fn update_inner_text(&self, text: &str) {
-        self.unwrap_text().borrow_mut().0 = text.to_string();
+    fn take_error(&self) -> Option<Error> {
+        self.check_errors().err()
     }
 
-    fn replace_children_with(&self, _node: &Self) {
-        unimplemented!()
+    /// Effects are stored on the node and only run when rendering with [`render_to_string_async`].
+    fn effect(&self, future: impl Future<Output = ()> + 'static) {
+        self.0.effects.borrow_mut().push(SsrEffect {
+            future: Box::pin(error_boundary::scoped(future)),
+            suspense: false,
+        });
     }
-    fn effect(&self, _future: impl std::future::Future<Output = ()> + 'static) {
-        // panic!("SsrNode does not support effects, please use SsrNodeAsync!")
+
+    fn suspense(&self, future: impl Future<Output = ()> + 'static) {
+        self.0.effects.borrow_mut().push(SsrEffect {
+            future: Box::pin(error_boundary::scoped(future)),
+            suspense: true,
+        });
     }
 }
 
@@ -260,8 +446,7 @@ impl fmt::Display for SsrNode {
 
 impl Render<SsrNode> for SsrNode {
     fn render_into(self: Box<Self>, parent: &SsrNode) -> Result<(), Error> {
-        parent.append_child(&self);
-        Ok(())
+        parent.append_built(&self)
     }
 }
 
@@ -272,8 +457,8 @@ pub struct Element {
     children: Fragment,
 }
 
-impl fmt::Display for Element {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Element {
+    fn write_start_tag(&self, f: &mut impl fmt::Write) -> fmt::Result {
         write!(f, "<{}", self.name)?;
         for (name, value) in &self.attributes {
             write!(
@@ -283,8 +468,19 @@ impl fmt::Display for Element {
                 html_escape::encode_double_quoted_attribute(value)
             )?;
         }
-        write!(f, ">{}</{}>", self.children, self.name)?;
-        Ok(())
+        write!(f, ">")
+    }
+
+    fn write_end_tag(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "</{}>", self.name)
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_start_tag(f)?;
+        write!(f, "{}", self.children)?;
+        self.write_end_tag(f)
     }
 }
 
@@ -318,17 +514,118 @@ impl fmt::Display for Fragment {
     }
 }
 
-/// Render a [`SsrNode`] into a static [`String`]. Useful for rendering to a string on the server side.
-pub fn render_to_string(dom: SsrNode) -> Result<String, Error> {
+/// Renders `dom` into a new fragment, then mounts the content built outside of any owner, see
+/// [`Owner::take_detached`].
+pub(crate) fn render_root(dom: SsrNode) -> Result<SsrNode, Error> {
+    let detached = Owner::take_detached();
     let root = SsrNode::fragment();
     Render::render_into(Box::new(dom), &root)?;
+    detached.mount();
+    Ok(root)
+}
+
+/// Render a [`SsrNode`] into a static [`String`]. Useful for rendering to a string on the server side.
+///
+/// Effects are not run, so a [`Suspense`](hirola_core::prelude::Suspense) renders its loading state.
+/// See [`render_to_string_async`] for rendering data that is loaded asynchronously.
+pub fn render_to_string(dom: SsrNode) -> Result<String, Error> {
+    let root = render_root(dom)?;
+    root.check_errors()?;
+    Ok(format!("{}", root))
+}
+
+/// Render a [`SsrNode`] into a [`String`] after running its effects.
+///
+/// All the effects registered on the tree are polled on the current task until every
+/// [`Suspense`](hirola_core::prelude::Suspense) has resolved and the remaining effects stop making progress.
+/// Effects that are still waiting after that, such as signal subscriptions, are dropped.
+///
+/// The returned future is not `Send`. See [`render_to_string_with_deadline`] to bound how long rendering waits.
+pub async fn render_to_string_async(dom: SsrNode) -> Result<String, Error> {
+    let root = render_root(dom)?;
+    executor::Settle::new(root.clone()).await;
+    root.check_errors()?;
+    Ok(format!("{}", root))
+}
+
+/// Like [`render_to_string_async`], but stops waiting once `deadline` completes.
+/// Anything that has not resolved by then is rendered in its current (eg. loading) state.
+///
+/// The deadline can be any future, such as a timer from your runtime:
+/// ```rust,ignore
+/// let html = render_to_string_with_deadline(page(), tokio::time::sleep(Duration::from_secs(2))).await?;
+/// ```
+pub async fn render_to_string_with_deadline(
+    dom: SsrNode,
+    deadline: impl Future<Output = ()>,
+) -> Result<String, Error> {
+    let root = render_root(dom)?;
+    let settle = executor::Settle::new(root.clone());
+    futures_util::pin_mut!(deadline);
+    let _ = future::select(settle, deadline).await;
+    root.check_errors()?;
     Ok(format!("{}", root))
 }
 
+/// Takes the stylesheet of the [`Styled`] components built since the previous call, with the rules
+/// of each component once.
+///
+/// Components are recorded as they are built, including the content built while rendering, eg.
+/// by a [`Suspense`](hirola_core::prelude::Suspense). Call it once per page, after rendering it.
+///
+/// [`Styled`]: hirola_core::templating::styled::Styled
+pub fn take_styles() -> String {
+    styled::take_collected_styles().to_css()
+}
+
+/// Inserts `css` as a `<style>` element at the end of the `<head>` of `html`.
+/// `html` is returned as is if it has no `<head>`.
+pub fn inject_styles(html: &str, css: &str) -> String {
+    match html.find("</head>") {
+        Some(end) if !css.is_empty() => {
+            // A `</style>` in the css would end the element early
+            let css = css.replace("</", "<\\/");
+            format!("{}<style>{}</style>{}", &html[..end], css, &html[end..])
+        }
+        _ => html.to_owned(),
+    }
+}
+
+/// Builds the page returned by `page` and renders it like [`render_to_string`], with the styles of
+/// the [`Styled`] components on the page injected into its `<head>`.
+///
+/// The styles are collected for this page alone, see [`styled::collect_styles`], so they are not
+/// left for [`take_styles`], even if rendering fails.
+///
+/// [`Styled`]: hirola_core::templating::styled::Styled
+pub fn render_to_string_with_styles(page: impl FnOnce() -> SsrNode) -> Result<String, Error> {
+    let (html, styles) = styled::collect_styles(|| render_to_string(page()));
+    Ok(inject_styles(&html?, &styles.to_css()))
+}
+
+/// Like [`render_to_string_with_styles`], rendering the page like [`render_to_string_async`]. The
+/// components built while rendering, eg. the content of a resolved
+/// [`Suspense`](hirola_core::prelude::Suspense), have their styles injected too.
+///
+/// See [`styled::collect_styles_async`].
+pub async fn render_to_string_async_with_styles(
+    page: impl FnOnce() -> SsrNode,
+) -> Result<String, Error> {
+    let (html, styles) =
+        styled::collect_styles_async(async { render_to_string_async(page()).await }).await;
+    Ok(inject_styles(&html?, &styles.to_css()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_util::{FutureExt, StreamExt};
     use hirola::prelude::*;
+    use hirola::signal::{Broadcaster, SignalExt};
+    use futures_util::task::noop_waker_ref;
+    use hirola_core::owner::Owner;
+    use std::cell::{Cell, RefCell};
+    use std::task::Context;
 
     #[test]
     fn hello_world() {
@@ -377,4 +674,1208 @@ mod tests {
         let dom = render_to_string(node).unwrap();
         assert_eq!("<ul><li>1</li><li>2</li><li>3</li><!----></ul>", dom);
     }
+
+    /// Completes on its second poll, after waking itself.
+    async fn yield_now() {
+        let mut yielded = false;
+        future::poll_fn(move |cx| {
+            if yielded {
+                std::task::Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                std::task::Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn async_runs_effects() {
+        let count = MutableVec::new_with_values(vec![1, 2, 3]);
+
+        let node = html! {
+                <ul>
+                    {count
+                        .signal_vec()
+                        .map_render(move |item| {
+                            html! { <li>{item.to_string()}</li> }
+                        })}
+                </ul>
+            };
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("effects should settle")
+            .unwrap();
+        assert_eq!("<ul><li>1</li><li>2</li><li>3</li><!----></ul>", dom);
+    }
+
+    #[test]
+    fn async_resolves_suspense() {
+        let node = html! {
+                <div>
+                    {match yield_now().map(|_| "Loaded").suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(text) => html! { <p>{text}</p> },
+                    }}
+                </div>
+            };
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("suspense should resolve")
+            .unwrap();
+        assert_eq!("<div><p>Loaded</p><!----></div>", dom);
+    }
+
+    #[test]
+    fn deadline_keeps_loading_state() {
+        let node = html! {
+                <div>
+                    {match future::pending::<&str>().suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(text) => html! { <p>{text}</p> },
+                    }}
+                </div>
+            };
+
+        let dom = render_to_string_with_deadline(node, future::ready(()))
+            .now_or_never()
+            .expect("deadline should stop rendering")
+            .unwrap();
+        assert_eq!("<div><p>Loading</p><!----></div>", dom);
+    }
+
+    #[test]
+    fn stream_sends_boundaries_as_they_resolve() {
+        let ready = Mutable::new(false);
+        let node = html! {
+                <div>
+                    {match ready.signal().wait_for(true).map(|_| "Loaded").suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(text) => html! { <p>{text}</p> },
+                    }}
+                </div>
+            };
+
+        let mut stream = render_to_stream(node).unwrap();
+        let shell = stream.next().now_or_never().flatten().unwrap();
+        assert_eq!(
+            "<div><!--hirola:0--><p>Loading</p><!--/hirola:0--><!----></div>",
+            shell
+        );
+        assert!(stream.next().now_or_never().is_none());
+
+        ready.set(true);
+        let chunk = stream.next().now_or_never().flatten().unwrap();
+        let chunk = std::str::from_utf8(&chunk).unwrap();
+        assert!(chunk.ends_with(
+            r#"<template id="hirola-0"><p>Loaded</p></template><script>__hirola_swap(0)</script>"#
+        ));
+        assert_eq!(stream.next().now_or_never(), Some(None));
+    }
+
+    #[test]
+    fn stream_sends_sibling_boundaries_separately() {
+        let first = Mutable::new(false);
+        let second = Mutable::new(false);
+        let node = html! {
+                <div>
+                    <h1>"Title"</h1>
+                    {match first.signal().wait_for(true).map(|_| "First").suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(text) => html! { <p>{text}</p> },
+                    }}
+                    {match second.signal().wait_for(true).map(|_| "Second").suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(text) => html! { <p>{text}</p> },
+                    }}
+                </div>
+            };
+
+        let mut stream = render_to_stream(node).unwrap();
+        let shell = stream.next().now_or_never().flatten().unwrap();
+        assert_eq!(
+            "<div><h1>Title</h1><!--hirola:0--><p>Loading</p><!--/hirola:0--><!----><!--hirola:1--><p>Loading</p><!--/hirola:1--><!----></div>",
+            shell
+        );
+
+        second.set(true);
+        let chunk = stream.next().now_or_never().flatten().unwrap();
+        let chunk = std::str::from_utf8(&chunk).unwrap();
+        assert!(chunk.ends_with(
+            r#"<template id="hirola-1"><p>Second</p></template><script>__hirola_swap(1)</script>"#
+        ));
+        assert!(!chunk.contains("hirola-0"));
+
+        first.set(true);
+        let chunk = stream.next().now_or_never().flatten().unwrap();
+        assert_eq!(
+            r#"<template id="hirola-0"><p>First</p></template><script>__hirola_swap(0)</script>"#,
+            chunk
+        );
+        assert_eq!(stream.next().now_or_never(), Some(None));
+    }
+
+    #[test]
+    fn suspense_renders_failures() {
+        let node = html! {
+                <div>
+                    {match future::ready(Err::<&str, _>("Oops")).try_suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(text) => html! { <p>{text}</p> },
+                        Failed(error) => html! { <p class="error">{error}</p> },
+                    }}
+                </div>
+            };
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("suspense should resolve")
+            .unwrap();
+        assert_eq!(r#"<div><p class="error">Oops</p><!----></div>"#, dom);
+    }
+
+    #[test]
+    fn suspense_try_new_renders_each_state() {
+        let node = SsrNode::element("div");
+        node.append_render(Suspense::try_new(
+            yield_now().map(|_| Err::<&str, _>("Oops")),
+            |state| match state {
+                Loading => html! { <p>"Loading"</p> },
+                Ready(text) => html! { <p>{text}</p> },
+                Failed(error) => html! { <p class="error">{error}</p> },
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            "<div><p>Loading</p><!----></div>",
+            render_to_string(node.clone()).unwrap()
+        );
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("suspense should resolve")
+            .unwrap();
+        assert_eq!(r#"<div><p class="error">Oops</p><!----></div>"#, dom);
+    }
+
+    #[test]
+    fn suspense_fallback_and_timeout() {
+        struct Page(&'static str);
+        let node = SsrNode::element("div");
+        node.append_render(
+            Suspense::new(future::pending(), |page: Page| html! { <p>{page.0}</p> })
+                .with_fallback(|| html! { <p>"Loading"</p> })
+                .with_timeout(yield_now().map(|_| Page("Timed out"))),
+        )
+        .unwrap();
+        assert_eq!(
+            "<div><p>Loading</p><!----></div>",
+            render_to_string(node.clone()).unwrap()
+        );
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("timeout should resolve")
+            .unwrap();
+        assert_eq!("<div><p>Timed out</p><!----></div>", dom);
+    }
+
+    #[test]
+    fn suspense_cancelled_when_removed() {
+        let ready = Mutable::new(false);
+        let shown = Mutable::new(true);
+        let rendered = Rc::new(Cell::new(false));
+        let loaded = rendered.clone();
+        let waiting = ready.clone();
+        let node: SsrNode = html! {
+                <main>
+                    {match shown.signal() as Signal {
+                        true => {
+                            let loaded = loaded.clone();
+                            let ready = waiting.clone();
+                            html! {
+                                <section>
+                                    {match ready.signal().wait_for(true).suspend().await {
+                                        Loading => html! { <p>"Loading"</p> },
+                                        Ready(_) => {
+                                            loaded.set(true);
+                                            html! { <p>"Loaded"</p> }
+                                        }
+                                    }}
+                                </section>
+                            }
+                        }
+                        false => html! { <p>"Gone"</p> },
+                    }}
+                </main>
+            };
+
+        let mut stream = render_to_stream(node.clone()).unwrap();
+        assert!(stream.next().now_or_never().flatten().is_some());
+
+        shown.set(false);
+        ready.set(true);
+        assert_eq!(stream.next().now_or_never(), Some(None));
+        assert!(!rendered.get());
+    }
+
+    #[test]
+    fn suspense_resolves_after_being_moved() {
+        let ready = Mutable::new(false);
+        let section: SsrNode = html! {
+                <section>
+                    {match ready.signal().wait_for(true).suspend().await {
+                        Loading => html! { <p>"Loading"</p> },
+                        Ready(_) => html! { <p>"Loaded"</p> },
+                    }}
+                </section>
+            };
+        // Polled while detached, then mounted
+        let mut executor = run(&section);
+        let node = html! { <main>{section.clone()}</main> };
+
+        ready.set(true);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            "<main><section><p>Loaded</p><!----></section></main>"
+        );
+    }
+
+    #[derive(Clone)]
+    enum Status {
+        Loading,
+        Empty,
+        List(Vec<&'static str>),
+    }
+
+    fn status_view(status: Status, count: i32) -> SsrNode {
+        let status = Mutable::new(status);
+        let count = Mutable::new(count);
+        html! {
+            <div>
+                {match status.signal_cloned() as Signal {
+                    Status::Loading => html! { <p>"Loading"</p> },
+                    Status::Empty => html! { <p>"Empty"</p> },
+                    Status::List(items) => html! { <p>{items.join(",")}</p> },
+                }}
+                {match count.signal() as Signal {
+                    0 => html! { <span>"None"</span> },
+                    n if n < 10 => html! { <span>"Few"</span> },
+                    _ => html! { <span>"Many"</span> },
+                }}
+            </div>
+        }
+    }
+
+    #[test]
+    fn match_renders_matching_arm() {
+        for (status, count, expected) in [
+            (Status::Loading, 0, "<div><p>Loading</p><!----><span>None</span><!----></div>"),
+            (Status::Empty, 3, "<div><p>Empty</p><!----><span>Few</span><!----></div>"),
+            (
+                Status::List(vec!["a", "b"]),
+                12,
+                "<div><p>a,b</p><!----><span>Many</span><!----></div>",
+            ),
+        ] {
+            let dom = render_to_string_async(status_view(status, count))
+                .now_or_never()
+                .expect("effects should settle")
+                .unwrap();
+            assert_eq!(expected, dom);
+        }
+    }
+
+    #[test]
+    fn match_signal_items_need_not_be_clone() {
+        struct Page(String);
+        let path = Mutable::new("home");
+        let node: SsrNode = html! {
+            <main>
+                {match path.signal_ref(|path| Page(path.to_string())) as Signal {
+                    Page(name) if name == "home" => html! { <h1>"Home"</h1> },
+                    Page(name) => html! { <h1>{name}</h1> },
+                }}
+            </main>
+        };
+        let mut executor = run(&node);
+        assert_eq!(node.to_string(), "<main><h1>Home</h1><!----></main>");
+
+        path.set("about");
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(node.to_string(), "<main><h1>about</h1><!----></main>");
+    }
+
+    #[test]
+    fn match_guards_run_once_and_bindings_keep_their_mode() {
+        let count = Mutable::new(1);
+        let checks = Rc::new(Cell::new(0));
+        let guards = checks.clone();
+        let node: SsrNode = html! {
+            <main>
+                {match count.signal().map(|n| (n, n.to_string())) as Signal {
+                    (n, ref text) if { guards.set(guards.get() + 1); n > 5 } => html! { <b>{text.clone()}</b> },
+                    (mut n, _) => {
+                        n *= 10;
+                        html! { <i>{n.to_string()}</i> }
+                    }
+                }}
+            </main>
+        };
+        let mut executor = run(&node);
+        assert_eq!(node.to_string(), "<main><i>10</i><!----></main>");
+        assert_eq!(checks.get(), 1);
+
+        count.set(7);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(node.to_string(), "<main><b>7</b><!----></main>");
+        assert_eq!(checks.get(), 2);
+    }
+
+    #[test]
+    fn renders_signals() {
+        let count = Mutable::new(2);
+        let name = Mutable::new("hirola");
+        let doubled = count.signal().map(|n| n * 2);
+        let greeting = map_ref! {
+            let name = name.signal(),
+            let count = count.signal() => format!("{name}{count}")
+        };
+        let broadcaster = Broadcaster::new(count.signal());
+
+        let node = html! {
+                <p>
+                    {doubled}
+                    {greeting}
+                    {broadcaster}
+                    {count.read_only()}
+                </p>
+            };
+
+        let dom = render_to_string(node).unwrap();
+        assert_eq!(
+            "<p><!---->4<!----><!---->hirola2<!----><!---->2<!---->2</p>",
+            dom
+        );
+    }
+
+    #[test]
+    fn renders_signals_of_display_values() {
+        #[derive(Clone)]
+        enum Status {
+            Online,
+            Away(u32),
+        }
+        impl std::fmt::Display for Status {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    Status::Online => f.write_str("Online"),
+                    Status::Away(minutes) => write!(f, "Away for {minutes} min"),
+                }
+            }
+        }
+        let status = Mutable::new(Status::Online);
+        let node: SsrNode = html! {
+            <p>
+                {status.clone()}
+                {status.signal_cloned().map(DisplayText)}
+            </p>
+        };
+        let mut executor = run(&node);
+        assert_eq!(node.to_string(), "<p>Online<!---->Online<!----></p>");
+
+        status.set(Status::Away(5));
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            "<p>Away for 5 min<!---->Away for 5 min<!----></p>"
+        );
+    }
+
+    #[test]
+    fn renders_signals_of_nodes() {
+        let show = Mutable::new(true);
+
+        let node: SsrNode = html! {
+                <div>
+                    {show.signal().map(|show| show.then(|| -> SsrNode { html! { <p>"Shown"</p> } }))}
+                    {show.signal().map(|show| -> SsrNode {
+                        if show {
+                            html! { <b>"Yes"</b> }
+                        } else {
+                            html! { <i>"No"</i> }
+                        }
+                    })}
+                </div>
+            };
+
+        let dom = render_to_string(node).unwrap();
+        assert_eq!(
+            "<div><!----><p>Shown</p><!----><!----><b>Yes</b><!----></div>",
+            dom
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_operations() {
+        let text = SsrNode::text_node("text");
+        let list = SsrNode::element("ul");
+
+        let error = text.append_child(&SsrNode::element("li")).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidOperation {
+                operation: "append_child",
+                tag: None,
+                ..
+            }
+        ));
+        assert_eq!(
+            "invalid node operation `insert_child_before`",
+            std::error::Error::source(&error).unwrap().to_string()
+        );
+        let error = list.remove_child(&text).unwrap_err();
+        assert_eq!("invalid node operation `remove_child` on <ul>", error.to_string());
+        assert!(matches!(
+            list.update_inner_text("text"),
+            Err(Error::FailedCast {
+                expected: "Text",
+                ..
+            })
+        ));
+        assert!(matches!(
+            text.replace_children_with(&list),
+            Err(Error::InvalidOperation {
+                operation: "replace_children_with",
+                ..
+            })
+        ));
+    }
+
+    /// Fails to render with [`Error::FailedCast`].
+    struct Broken;
+
+    impl Render<SsrNode> for Broken {
+        fn render_into(self: Box<Self>, parent: &SsrNode) -> Result<(), Error> {
+            parent.set_attribute("broken", "true")?;
+            SsrNode::text_node("text").set_attribute("broken", "true")
+        }
+    }
+
+    #[test]
+    fn returns_render_errors() {
+        let node = html! { <div><p>{Broken}</p></div> };
+
+        assert!(matches!(
+            render_to_string(node),
+            Err(Error::FailedCast {
+                expected: "Element",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn render_into_returns_build_errors() {
+        let node = html! { <div><p>{Broken}</p></div> };
+        let parent = SsrNode::element("main");
+
+        assert!(matches!(
+            Render::render_into(Box::new(node), &parent),
+            Err(Error::FailedCast {
+                expected: "Element",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn replaces_children() {
+        let list: SsrNode = html! { <ul><li>"One"</li><li>"Two"</li></ul> };
+
+        list.replace_children_with(&html! { <li>"Three"</li> })
+            .unwrap();
+
+        assert_eq!("<ul><li>Three</li></ul>", list.to_string());
+    }
+
+    fn error_view(error: Error, _: Retry) -> SsrNode {
+        html! { <p class="error">{error.to_string()}</p> }
+    }
+
+    #[test]
+    fn error_boundary_renders_fallback() {
+        let node = html! {
+                <main>
+                    {ErrorBoundary::new(|| html! { <div><p>{Broken}</p></div> }, error_view)}
+                    <p>"Still here"</p>
+                </main>
+            };
+
+        assert_eq!(
+            r#"<main><p class="error">expected a node of type `Element`</p><!----><p>Still here</p></main>"#,
+            render_to_string(node).unwrap()
+        );
+    }
+
+    #[test]
+    fn error_boundary_catches_panics() {
+        let boundary = ErrorBoundary::new(
+            || -> SsrNode { panic!("widget panicked") },
+            error_view,
+        )
+        .catch_panics();
+        let node = html! { <main>{boundary}</main> };
+
+        assert_eq!(
+            r#"<main><p class="error">panicked: widget panicked</p><!----></main>"#,
+            render_to_string(node).unwrap()
+        );
+    }
+
+    #[test]
+    fn error_boundary_catches_effect_errors() {
+        let boundary = ErrorBoundary::new(
+            || {
+                let node: SsrNode = html! { <p>"Loading"</p> };
+                let failing = node.clone();
+                node.effect(async move {
+                    yield_now().await;
+                    failing.report_error(Error::Missing("data"));
+                });
+                node
+            },
+            error_view,
+        );
+        let node = html! { <main>{boundary}</main> };
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("effects should settle")
+            .unwrap();
+        assert_eq!(
+            r#"<main><p class="error">could not acquire data</p><!----></main>"#,
+            dom
+        );
+    }
+
+    #[test]
+    fn error_boundary_retries() {
+        let attempts = Rc::new(Cell::new(0));
+        let retry = Rc::new(RefCell::new(None));
+        let boundary = {
+            let attempts = attempts.clone();
+            let retry = retry.clone();
+            ErrorBoundary::new(
+                move || {
+                    attempts.set(attempts.get() + 1);
+                    match attempts.get() {
+                        1 => html! { <p>{Broken}</p> },
+                        _ => html! { <p>"Loaded"</p> },
+                    }
+                },
+                move |error, handle: Retry| {
+                    *retry.borrow_mut() = Some(handle.clone());
+                    error_view(error, handle)
+                },
+            )
+        };
+        let node = html! { <main>{boundary}</main> };
+        assert_eq!(
+            r#"<main><p class="error">expected a node of type `Element`</p><!----></main>"#,
+            render_to_string(node.clone()).unwrap()
+        );
+
+        retry.take().unwrap().retry();
+        assert_eq!(2, attempts.get());
+        assert_eq!(
+            "<main><p>Loaded</p><!----></main>",
+            render_to_string(node).unwrap()
+        );
+    }
+
+    #[test]
+    fn portal_renders_into_target() {
+        let target = SsrNode::element("aside");
+        let owner = Owner::default();
+        let node: SsrNode = owner.run(|| {
+            html! {
+                <main>
+                    {Portal::new(target.clone(), html! { <p>"Modal"</p> })}
+                </main>
+            }
+        });
+
+        assert_eq!("<main></main>", render_to_string(node).unwrap());
+        assert_eq!("<aside><!----><p>Modal</p><!----></aside>", target.to_string());
+
+        owner.dispose();
+        assert_eq!("<aside></aside>", target.to_string());
+    }
+
+    #[test]
+    fn portal_renders_into_named_root() {
+        let node = html! {
+                <body>
+                    <div>{PortalRoot { name: "modals" }}</div>
+                    <main>
+                        {Portal::to_root("modals", html! { <dialog>"Saved"</dialog> })}
+                    </main>
+                </body>
+            };
+        assert_eq!(
+            "<body><div><!----><dialog>Saved</dialog><!----></div><main></main></body>",
+            render_to_string(node).unwrap()
+        );
+
+        let node: SsrNode =
+            html! { <main>{Portal::to_root("toasts", html! { <p>"Saved"</p> })}</main> };
+        let error = render_to_string(node).unwrap_err();
+        assert!(matches!(&error, Error::MissingPortalRoot(name) if name == "toasts"));
+        assert_eq!("no portal root is registered as `toasts`", error.to_string());
+    }
+
+    #[derive(Clone)]
+    struct Theme(&'static str);
+
+    fn themed() -> SsrNode {
+        let theme = use_context::<Theme>().map_or("none", |theme| theme.0);
+        html! { <span>{theme}</span> }
+    }
+
+    #[test]
+    fn context_is_scoped_to_subtree() {
+        let dark = Mutable::new(true);
+        let node = html! {
+            <main>
+                {themed()}
+                {with_context(Theme("dark"), || html! {
+                    <div>
+                        {themed()}
+                        {with_context(Theme("light"), themed)}
+                        {match dark.signal() as Signal {
+                            true => themed(),
+                            false => with_context(Theme("light"), themed),
+                        }}
+                    </div>
+                })}
+                {themed()}
+            </main>
+        };
+        assert_eq!(
+            "<main><span>none</span><div><span>dark</span><span>light</span><span>dark</span><!----></div><span>none</span></main>",
+            render_to_string_async(node)
+                .now_or_never()
+                .expect("effects should settle")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn context_provided_to_rest_of_owner() {
+        let owner = Owner::default();
+        let (before, after) = owner.run(|| {
+            let before = themed();
+            provide_context(Theme("dark"));
+            (before, themed())
+        });
+        assert_eq!("<span>none</span>", before.to_string());
+        assert_eq!("<span>dark</span>", after.to_string());
+
+        provide_context(Theme("dark"));
+        assert_eq!("<span>none</span>", themed().to_string());
+    }
+
+    /// A component that records when it is mounted and cleaned up.
+    fn tracked(name: &'static str, log: &Rc<RefCell<Vec<String>>>) -> SsrNode {
+        let mounted = log.clone();
+        let cleaned = log.clone();
+        on_mount(move || {
+            mounted.borrow_mut().push(format!("mount {name}"));
+            on_cleanup(move || cleaned.borrow_mut().push(format!("cleanup {name}")));
+        });
+        html! { <p>{name}</p> }
+    }
+
+    /// Renders `node` and runs its effects until they stall.
+    fn run(node: &SsrNode) -> executor::Executor {
+        let root = SsrNode::fragment();
+        root.append_child(node).unwrap();
+        let mut executor = executor::Executor::new(root);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        executor
+    }
+
+    #[test]
+    fn lifecycle_hooks_run_when_match_switches() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let first = Mutable::new(true);
+        let tracker = log.clone();
+        let node = html! {
+            <main>
+                {match first.signal() as Signal {
+                    true => tracked("first", &tracker),
+                    false => tracked("second", &tracker),
+                }}
+            </main>
+        };
+        let mut executor = run(&node);
+        assert_eq!(*log.borrow(), ["mount first"]);
+
+        first.set(false);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!("<main><p>second</p><!----></main>", node.to_string());
+        assert_eq!(
+            *log.borrow(),
+            ["mount first", "cleanup first", "mount second"]
+        );
+    }
+
+    #[test]
+    fn lifecycle_hooks_run_for_indexed_rows() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let rows = MutableVec::new_with_values(vec!["a", "b"]);
+        let tracker = log.clone();
+        let node = html! {
+            <ul>
+                {rows.signal_vec().map_render(move |row| {
+                    // Nested content mounts along with the row it is in, before it
+                    let nested = tracker.clone();
+                    let boundary = ErrorBoundary::new(
+                        move || tracked("nested", &nested),
+                        |_, _| html! { <></> },
+                    );
+                    html! { <li>{tracked(row, &tracker)}{boundary}</li> }
+                })}
+            </ul>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            *log.borrow(),
+            ["mount nested", "mount a", "mount nested", "mount b"]
+        );
+
+        log.borrow_mut().clear();
+        rows.lock_mut().remove(0);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(*log.borrow(), ["cleanup nested", "cleanup a"]);
+    }
+
+    #[test]
+    fn lifecycle_hooks_run_inside_with_context() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let shown = Mutable::new(true);
+        let tracker = log.clone();
+        let node = html! {
+            <main>
+                {match shown.signal() as Signal {
+                    true => with_context(Theme("dark"), || tracked("themed", &tracker)),
+                    false => html! { <></> },
+                }}
+            </main>
+        };
+        let mut executor = run(&node);
+        assert_eq!(*log.borrow(), ["mount themed"]);
+
+        shown.set(false);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(*log.borrow(), ["mount themed", "cleanup themed"]);
+    }
+
+    #[test]
+    fn root_lifecycle_hooks_run_once_rendered() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let node = html! {
+            <main>
+                {tracked("root", &log)}
+                {with_context(Theme("dark"), || tracked("themed", &log))}
+            </main>
+        };
+        assert!(log.borrow().is_empty());
+
+        render_to_string(node).unwrap();
+        assert_eq!(*log.borrow(), ["mount themed", "mount root"]);
+    }
+
+    #[test]
+    fn async_waits_for_resource() {
+        let user = Resource::new(Mutable::new(2).signal(), |id| {
+            yield_now().map(move |_| Ok::<_, String>(format!("User {id}")))
+        });
+        let node = html! {
+            <div>
+                {user.render(|state| match state {
+                    Loading => html! { <p>"Loading"</p> },
+                    Ready(name) => html! { <p>{name}</p> },
+                    Failed(error) => html! { <p class="error">{error}</p> },
+                })}
+            </div>
+        };
+
+        let dom = render_to_string_async(node)
+            .now_or_never()
+            .expect("resource should load")
+            .unwrap();
+        assert_eq!("<div><p>User 2</p><!----><!----></div>", dom);
+    }
+
+    #[test]
+    fn resource_drops_stale_responses() {
+        let id = Mutable::new(1);
+        let released = Mutable::new(Vec::new());
+        let responses = released.clone();
+        let user = Resource::new(id.signal(), move |id| {
+            responses
+                .signal_ref(move |released: &Vec<u32>| released.contains(&id))
+                .wait_for(true)
+                .map(move |_| Ok::<_, ()>(id))
+        });
+        let node = html! {
+            <p>
+                {user.render(|state| match state {
+                    Ready(id) => html! { <span>{id.to_string()}</span> },
+                    _ => html! { <span>"Loading"</span> },
+                })}
+            </p>
+        };
+        let mut executor = run(&node);
+        let mut settle = || executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(node.to_string(), "<p><span>Loading</span><!----><!----></p>");
+
+        id.set(2);
+        released.lock_mut().push(2);
+        settle();
+        released.lock_mut().push(1);
+        settle();
+        assert_eq!(node.to_string(), "<p><span>2</span><!----><!----></p>");
+
+        user.mutate(3);
+        settle();
+        assert_eq!(user.get(), Ready(3));
+        assert_eq!(node.to_string(), "<p><span>3</span><!----></p>");
+
+        user.refetch();
+        settle();
+        assert_eq!(node.to_string(), "<p><span>2</span><!----><!----></p>");
+    }
+
+    #[test]
+    fn resource_loads_again_once_rendered_again() {
+        let id = Mutable::new(1);
+        let page = Mutable::new(1);
+        let user = Resource::new(id.signal(), |id| {
+            yield_now().map(move |_| Ok::<_, ()>(format!("User {id}")))
+        });
+        let view = |user: &Resource<u32, String, ()>| {
+            user.render(|state| match state {
+                Ready(name) => html! { <span>{name}</span> },
+                _ => html! { <span>"Loading"</span> },
+            })
+        };
+        let node = html! {
+            <main>
+                {match page.signal() as Signal {
+                    1 => html! { <p>{view(&user)}</p> },
+                    _ => html! { <div>{view(&user)}</div> },
+                }}
+            </main>
+        };
+        let mut executor = run(&node);
+        let mut settle = || executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        settle();
+        assert_eq!(
+            node.to_string(),
+            "<main><p><span>User 1</span><!----><!----></p><!----></main>"
+        );
+
+        // The first render is disposed once the second one is rendered
+        page.set(2);
+        settle();
+        id.set(2);
+        settle();
+        assert_eq!(
+            node.to_string(),
+            "<main><div><span>User 2</span><!----><!----></div><!----></main>"
+        );
+    }
+
+    #[test]
+    fn resource_released_with_its_nodes() {
+        let loader = Rc::new(());
+        let released = Rc::downgrade(&loader);
+        let user = Resource::new(Mutable::new(1).signal(), move |_| {
+            let _loader = Rc::clone(&loader);
+            std::future::pending::<Result<u32, ()>>()
+        });
+        // Rendered outside of any owner, the loads are only dropped with the nodes
+        let node = SsrNode::element("p");
+        node.append_render(user.render(|state| match state {
+            Ready(id) => html! { <span>{id.to_string()}</span> },
+            _ => html! { <span>"Loading"</span> },
+        }))
+        .unwrap();
+        let mut executor = run(&node);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(node.to_string(), "<p><span>Loading</span><!----><!----></p>");
+
+        // Kept on the nodes once nothing runs their effects anymore
+        drop(executor);
+        user.refetch();
+        drop((user, node));
+        assert!(released.upgrade().is_none());
+    }
+
+    struct Badge;
+
+    impl Styled for Badge {
+        fn style() -> Style {
+            style! {
+                ".badge > b" {
+                    "color": "red";
+                }
+            }
+        }
+    }
+
+    fn badge(text: &'static str) -> SsrNode {
+        Badge::styled(html! { <span class=Badge::class("badge")><b>{text}</b></span> })
+    }
+
+    #[test]
+    fn styles_injected_into_head_once_per_page() {
+        let page = || {
+            html! {
+                <html>
+                    <head><title>"Badges"</title></head>
+                    <body>{badge("One")}{badge("Two")}</body>
+                </html>
+            }
+        };
+        let class = Badge::class("badge");
+        let css = format!(".{class} > b{{color:red;}}");
+
+        for _ in 0..2 {
+            let html = render_to_string_with_styles(page).unwrap();
+            assert!(html.starts_with(&format!(
+                "<html><head><title>Badges</title><style>{css}</style></head>"
+            )));
+        }
+        let broken = || html! { <head>{badge("One")}{Broken}</head> };
+        assert!(render_to_string_with_styles(broken).is_err());
+        assert_eq!(take_styles(), "");
+        assert_eq!(
+            inject_styles("<head></head>", "p::after{content:\"</style>\"}"),
+            r#"<head><style>p::after{content:"<\/style>"}</style></head>"#
+        );
+    }
+
+    #[test]
+    fn styles_of_resolved_content_injected_with_async_render() {
+        let page = || {
+            html! {
+                <html>
+                    <head></head>
+                    <body>
+                        {match yield_now().suspend().await {
+                            Loading => html! { <p>"Loading"</p> },
+                            Ready(_) => badge("Loaded"),
+                            Failed(_) => html! { <p>"Failed"</p> },
+                        }}
+                    </body>
+                </html>
+            }
+        };
+        let class = Badge::class("badge");
+
+        let html = render_to_string_async_with_styles(page)
+            .now_or_never()
+            .expect("page should render")
+            .unwrap();
+        assert!(html.starts_with(&format!(
+            "<html><head><style>.{class} > b{{color:red;}}</style></head>"
+        )));
+        assert!(html.contains("<b>Loaded</b>"));
+        assert_eq!(take_styles(), "");
+    }
+
+    #[test]
+    fn boolean_and_optional_attributes() {
+        let title = Mutable::new(Some("Saved"));
+        let hidden = Mutable::new(true);
+        let node = html! {
+            <p>
+                <button disabled=false>"Save"</button>
+                <button disabled=true>"Undo"</button>
+                <span title={None::<&str>}>"Plain"</span>
+                <span title=title.signal().map(|title| title)>"Status"</span>
+                <b hidden=hidden.signal().map(|hidden| hidden)>"New"</b>
+            </p>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            node.to_string(),
+            r#"<p><button>Save</button><button disabled="">Undo</button><span>Plain</span><span title="Saved">Status</span><b hidden="">New</b></p>"#
+        );
+
+        title.set(None);
+        hidden.set(false);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            "<p><button>Save</button><button disabled=\"\">Undo</button><span>Plain</span><span>Status</span><b>New</b></p>"
+        );
+    }
+
+    #[derive(Clone, PartialEq)]
+    enum Density {
+        Compact,
+        Roomy,
+    }
+
+    impl fmt::Display for Density {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Density::Compact => "compact",
+                Density::Roomy => "roomy",
+            })
+        }
+    }
+
+    #[test]
+    fn displayed_attributes() {
+        let density = Mutable::new(Density::Compact);
+        let node = html! {
+            <main data-density=density.signal_cloned().map(Displayed)>
+                <p data-density=Displayed(Density::Roomy)>"Text"</p>
+            </main>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            node.to_string(),
+            r#"<main data-density="compact"><p data-density="roomy">Text</p></main>"#
+        );
+
+        density.set(Density::Roomy);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            r#"<main data-density="roomy"><p data-density="roomy">Text</p></main>"#
+        );
+    }
+
+    #[test]
+    fn class_and_style_directives_merge_with_attributes() {
+        let active = Mutable::new(true);
+        let width = Mutable::new(Some("10px".to_owned()));
+        let node = html! {
+            <div>
+                <p class="card" class:is-active=active.signal() class:hidden=false>"Card"</p>
+                <p style="color: red;" style:width=width.clone() style:font-size="2em">"Text"</p>
+            </div>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            node.to_string(),
+            r#"<div><p class="card is-active">Card</p><p style="color: red; font-size: 2em; width: 10px;">Text</p></div>"#
+        );
+
+        active.set(false);
+        width.set(None);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            r#"<div><p class="card">Card</p><p style="color: red; font-size: 2em;">Text</p></div>"#
+        );
+    }
+
+    #[test]
+    fn class_and_style_directives_take_any_signal() {
+        let active = Mutable::new(true);
+        let pinned = Mutable::new(false);
+        let image = "url(data:image/png;base64,AAA)";
+        let shown = map_ref! {
+            let active = active.signal(), let pinned = pinned.signal() => *active && !*pinned
+        };
+        let node = html! {
+            <div>
+                <p class:is-active=shown class:pinned=pinned.signal_ref(|pinned| *pinned)>"Card"</p>
+                <p
+                    style=format!("background: {image}; content: 'a;b'")
+                    style:width="10px"
+                    style:opacity=pinned.signal_ref(|pinned| pinned.then_some("0.5"))
+                >
+                    "Text"
+                </p>
+            </div>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            node.to_string(),
+            format!(
+                r#"<div><p class="is-active">Card</p><p style="background: {image}; content: 'a;b'; width: 10px;">Text</p></div>"#
+            )
+        );
+
+        pinned.set(true);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            format!(
+                r#"<div><p class="pinned">Card</p><p style="background: {image}; content: 'a;b'; width: 10px; opacity: 0.5;">Text</p></div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn prop_directive_renders_reflected_attributes() {
+        let checked = Mutable::new(true);
+        let node = html! {
+            <div>
+                <input prop:value="Draft" prop:indeterminate=true/>
+                <input prop:checked=checked.signal()/>
+                <label prop:htmlFor="name">"Name"</label>
+            </div>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            node.to_string(),
+            r#"<div><input value="Draft"></input><input checked=""></input><label for="name">Name</label></div>"#
+        );
+
+        checked.set(false);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            r#"<div><input value="Draft"></input><input></input><label for="name">Name</label></div>"#
+        );
+    }
+
+    #[test]
+    fn prop_directive_takes_any_signal() {
+        let name = Mutable::new("Draft".to_owned());
+        let required = Mutable::new(false);
+        let node = html! {
+            <div>
+                <input prop:value=name.signal_ref(|name| name.to_uppercase())/>
+                <input prop:required=required.signal().dedupe().boxed_local()/>
+            </div>
+        };
+        let mut executor = run(&node);
+        assert_eq!(
+            node.to_string(),
+            r#"<div><input value="DRAFT"></input><input></input></div>"#
+        );
+
+        name.set("Saved".to_owned());
+        required.set(true);
+        executor.run_until_stalled(&mut Context::from_waker(noop_waker_ref()));
+        assert_eq!(
+            node.to_string(),
+            r#"<div><input value="SAVED"></input><input required=""></input></div>"#
+        );
+    }
 }
@@ -0,0 +1,214 @@
+//! Streaming server side rendering with out-of-order [`Suspense`] boundaries.
+//!
+//! The page is first sent with every pending boundary showing its loading state. The content of each
+//! boundary is delimited by its own pair of `<!--hirola:{id}-->` / `<!--/hirola:{id}-->` comments. When the future of a
+//! boundary resolves, its new content is sent in a `<template>` together with a small inline script
+//! that swaps it in between the comments.
+//!
+//! [`Suspense`]: hirola_core::prelude::Suspense
+use std::fmt::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::Stream;
+use hirola_core::generic_node::GenericNode;
+use hirola_core::render::Error;
+
+use crate::executor::Executor;
+use crate::{render_root, SsrNode, SsrNodeType};
+
+/// Defines `__hirola_swap(id)`, which moves the content of the `hirola-{id}` template
+/// between the comments of boundary `id`.
+const SWAP_SCRIPT: &str = r#"<script>function __hirola_swap(i){var t=document.getElementById("hirola-"+i),w=document.createTreeWalker(document,128),s,e,n;while(w.nextNode()){n=w.currentNode;if(n.data=="hirola:"+i)s=n;else if(n.data=="/hirola:"+i){e=n;break}}if(s&&e){while((n=s.nextSibling)&&n!==e)n.remove();e.before(t.content)}t.remove()}</script>"#;
+
+/// A [`Stream`] of html chunks produced by [`render_to_stream`].
+pub struct SsrStream {
+    root: SsrNode,
+    executor: Executor,
+    /// The markers of the boundaries that had a pending [`GenericNode::suspense`] future when they
+    /// were sent, with their ids.
+    ///
+    /// [`GenericNode::suspense`]: hirola_core::generic_node::GenericNode::suspense
+    boundaries: Vec<(SsrNode, usize)>,
+    shell_sent: bool,
+    script_sent: bool,
+}
+
+impl fmt::Debug for SsrStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SsrStream")
+            .field("root", &self.root)
+            .field("shell_sent", &self.shell_sent)
+            .finish()
+    }
+}
+
+/// Render a [`SsrNode`] into a [`Stream`] of html chunks.
+///
+/// The first chunk is the whole page, with every [`Suspense`] that is still waiting showing its
+/// loading state. Each following chunk carries the content of a boundary that has since resolved,
+/// along with the script that swaps it into place. Boundaries are sent in the order they resolve,
+/// not the order they appear in the page. The stream ends once no boundary is left waiting.
+///
+/// Like [`render_to_string_async`](crate::render_to_string_async), the stream is driven on the
+/// task that polls it and is not `Send`.
+///
+/// The styles of the [`Styled`] components are not injected, since the `<head>` is sent before
+/// the boundaries resolve. Call [`take_styles`](crate::take_styles) once the stream ends, and send
+/// them yourself, eg. in a `<style>` element of the last chunk.
+///
+/// [`Suspense`]: hirola_core::prelude::Suspense
+/// [`Styled`]: hirola_core::templating::styled::Styled
+pub fn render_to_stream(dom: SsrNode) -> Result<SsrStream, Error> {
+    let root = render_root(dom)?;
+    root.check_errors()?;
+    Ok(SsrStream {
+        executor: Executor::new(root.clone()),
+        root,
+        boundaries: Vec::new(),
+        shell_sent: false,
+        script_sent: false,
+    })
+}
+
+impl SsrStream {
+    /// Gives an id to every boundary that is currently waiting on a suspense future.
+    fn track_boundaries(&mut self) {
+        let suspended: Vec<SsrNode> = self.executor.suspended().cloned().collect();
+        for host in suspended {
+            if self.boundary(&host).is_none() {
+                let id = self.boundaries.len();
+                self.boundaries.push((host, id));
+            }
+        }
+    }
+
+    fn boundary(&self, node: &SsrNode) -> Option<usize> {
+        self.boundaries
+            .iter()
+            .find_map(|(host, id)| (host == node).then_some(*id))
+    }
+
+    /// Whether `node` is still part of the rendered tree.
+    fn is_attached(&self, node: &SsrNode) -> bool {
+        let mut current = Some(node.clone());
+        while let Some(node) = current {
+            if node == self.root {
+                return true;
+            }
+            current = node.parent_node();
+        }
+        false
+    }
+
+    fn write_node(&self, node: &SsrNode, f: &mut String) -> fmt::Result {
+        match node.0.ty.as_ref() {
+            SsrNodeType::Element(element) => {
+                let element = element.borrow();
+                element.write_start_tag(f)?;
+                self.write_children(node, f)?;
+                element.write_end_tag(f)
+            }
+            SsrNodeType::Fragment(_) => self.write_children(node, f),
+            _ => write!(f, "{}", node),
+        }
+    }
+
+    /// Writes the children of `node`, delimiting the content of every boundary, ie. the fragment
+    /// right before the marker of a suspense, with its comments.
+    fn write_children(&self, node: &SsrNode, f: &mut String) -> fmt::Result {
+        let children = node.children().take();
+        let mut written = false;
+        for (index, child) in children.iter().enumerate() {
+            if written {
+                written = false;
+            } else if let Some(id) = self.boundary(child) {
+                // The boundary has no content
+                write!(f, "<!--hirola:{id}--><!--/hirola:{id}-->")?;
+            }
+            let boundary = children
+                .get(index + 1)
+                .and_then(|next| self.boundary(next))
+                .filter(|_| is_fragment(child));
+            match boundary {
+                Some(id) => {
+                    write!(f, "<!--hirola:{id}-->")?;
+                    self.write_node(child, f)?;
+                    write!(f, "<!--/hirola:{id}-->")?;
+                    written = true;
+                }
+                None => self.write_node(child, f)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn shell(&mut self) -> String {
+        self.track_boundaries();
+        let mut html = String::new();
+        self.write_node(&self.root.clone(), &mut html)
+            .expect("writing to a String cannot fail");
+        html
+    }
+
+    fn resolved(&mut self, hosts: Vec<SsrNode>) -> String {
+        self.track_boundaries();
+        let mut html = String::new();
+        let mut sent = Vec::new();
+        for host in hosts {
+            let Some(id) = self.boundary(&host) else {
+                continue;
+            };
+            if sent.contains(&id) || !self.is_attached(&host) {
+                continue;
+            }
+            sent.push(id);
+            if !self.script_sent {
+                self.script_sent = true;
+                html.push_str(SWAP_SCRIPT);
+            }
+            html.push_str(&format!(r#"<template id="hirola-{id}">"#));
+            if let Some(content) = content_of(&host) {
+                self.write_node(&content, &mut html)
+                    .expect("writing to a String cannot fail");
+            }
+            html.push_str(&format!("</template><script>__hirola_swap({id})</script>"));
+        }
+        html
+    }
+}
+
+fn is_fragment(node: &SsrNode) -> bool {
+    matches!(node.0.ty.as_ref(), SsrNodeType::Fragment(_))
+}
+
+/// The fragment holding the content of the boundary that ends with `marker`.
+fn content_of(marker: &SsrNode) -> Option<SsrNode> {
+    let children = marker.parent_node()?.children().take();
+    let index = children.iter().position(|child| child == marker)?;
+    children
+        .get(index.checked_sub(1)?)
+        .filter(|child| is_fragment(child))
+        .cloned()
+}
+
+impl Stream for SsrStream {
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        let resolved = self.executor.run_until_stalled(cx);
+        if !self.shell_sent {
+            self.shell_sent = true;
+            return Poll::Ready(Some(Bytes::from(self.shell())));
+        }
+        let html = self.resolved(resolved);
+        if !html.is_empty() {
+            Poll::Ready(Some(Bytes::from(html)))
+        } else if self.executor.suspended().next().is_none() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
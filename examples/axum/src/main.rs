@@ -9,6 +9,7 @@ mod layout;
 use crate::layout::*;
 
 use axum::{
+    body::Body,
     http::StatusCode,
     response::{Html, IntoResponse, Response},
     routing::get,
@@ -16,8 +17,11 @@ use axum::{
 };
 use hirola::{
     prelude::*,
-    ssr::{render_to_string, SsrNode},
+    ssr::{render_to_stream, render_to_string, render_to_string_with_deadline, SsrNode},
 };
+use futures_util::StreamExt;
+use std::{convert::Infallible, time::Duration};
+use tokio::runtime::Handle;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 async fn home() -> impl IntoResponse {
@@ -50,15 +54,114 @@ async fn home() -> impl IntoResponse {
     SsrTemplate(template)
 }
 
+async fn load_riders() -> Vec<&'static str> {
+    // Stands in for a database query or an API call
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    vec!["Jude Bellingham", "Kylian Mbappe", "Vinicius Junior"]
+}
+
+fn riders_page() -> SsrNode {
+    html! {
+        <main>
+            <h2>"Riders"</h2>
+            {match load_riders().suspend().await {
+                Loading => html! { <p>"Loading..."</p> },
+                Ready(riders) => {
+                    html! {
+                        <ul>
+                            {for rider in riders {
+                                html! { <li>{rider}</li> }
+                            }}
+                        </ul>
+                    }
+                }
+            }}
+        </main>
+    }
+}
+
+async fn riders() -> impl IntoResponse {
+    // `SsrNode` is not `Send`, so the page is built and awaited on a blocking thread
+    let html = tokio::task::spawn_blocking(|| {
+        Handle::current().block_on(render_to_string_with_deadline(
+            riders_page(),
+            tokio::time::sleep(Duration::from_secs(2)),
+        ))
+        .map_err(|err| format!("{err:?}"))
+    })
+    .await
+    .expect("render task panicked");
+    SsrHtml(html)
+}
+
+async fn load_stat(name: &'static str, delay: u64) -> String {
+    tokio::time::sleep(Duration::from_millis(delay)).await;
+    format!("{name}: {delay}")
+}
+
+fn dashboard_page() -> SsrNode {
+    html! {
+        <main>
+            <h2>"Dashboard"</h2>
+            <section>
+                {match load_stat("Slow query", 1500).suspend().await {
+                    Loading => html! { <p>"Loading slow query..."</p> },
+                    Ready(stat) => html! { <p>{stat}</p> },
+                }}
+            </section>
+            <section>
+                {match load_stat("Fast query", 300).suspend().await {
+                    Loading => html! { <p>"Loading fast query..."</p> },
+                    Ready(stat) => html! { <p>{stat}</p> },
+                }}
+            </section>
+        </main>
+    }
+}
+
+async fn dashboard() -> Response {
+    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    // `SsrStream` is not `Send`, so it is driven on a blocking thread and forwarded to the body
+    tokio::task::spawn_blocking(move || {
+        Handle::current().block_on(async move {
+            let mut stream = match render_to_stream(dashboard_page()) {
+                Ok(stream) => stream,
+                Err(err) => {
+                    tracing::error!("Failed to render template. Error: {err:?}");
+                    return;
+                }
+            };
+            while let Some(chunk) = stream.next().await {
+                if tx.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+        })
+    });
+    let body = futures_util::stream::unfold(rx, |mut rx| async move {
+        let chunk = rx.recv().await?;
+        Some((Ok::<_, Infallible>(chunk), rx))
+    });
+    Html(Body::from_stream(body)).into_response()
+}
+
 struct SsrTemplate(SsrNode);
 
 impl IntoResponse for SsrTemplate {
     fn into_response(self) -> Response {
-        match render_to_string(self.0) {
+        SsrHtml(render_to_string(self.0).map_err(|err| format!("{err:?}"))).into_response()
+    }
+}
+
+struct SsrHtml(Result<String, String>);
+
+impl IntoResponse for SsrHtml {
+    fn into_response(self) -> Response {
+        match self.0 {
             Ok(html) => Html(html).into_response(),
             Err(err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to render template. Error: {err:?}"),
+                format!("Failed to render template. Error: {err}"),
             )
                 .into_response(),
         }
@@ -75,7 +178,10 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let app = Router::new().route("/", get(home));
+    let app = Router::new()
+        .route("/", get(home))
+        .route("/riders", get(riders))
+        .route("/dashboard", get(dashboard));
 
     // run it
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
@@ -15,7 +15,7 @@
 /// The defaults imports
 pub mod prelude {
     pub use hirola_core::prelude::*;
-    pub use hirola_macros::{component, html, mixin, FormEntity};
+    pub use hirola_macros::{component, html, mixin, FormEntity, Store};
 }
 
 /// Exposing single item signal